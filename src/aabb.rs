@@ -0,0 +1,68 @@
+use crate::vertex::Vertex;
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+// Caja alineada con los ejes que envuelve un conjunto de puntos
+#[derive(Clone, Copy, Debug)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    // Caja de las posiciones (en espacio del modelo) de un arreglo de vértices
+    pub fn from_vertices(vertices: &[Vertex]) -> Self {
+        Self::from_points(vertices.iter().map(|vertex| vertex.position))
+    }
+
+    fn from_points(points: impl IntoIterator<Item = Vec3>) -> Self {
+        let mut min = Vec3::repeat(f32::INFINITY);
+        let mut max = Vec3::repeat(f32::NEG_INFINITY);
+        for point in points {
+            min = min.inf(&point);
+            max = max.sup(&point);
+        }
+        Aabb { min, max }
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    // Caja en espacio del mundo: se transforman las 8 esquinas y se vuelven a envolver, así
+    // que con rotación la caja queda algo más holgada que el objeto
+    pub fn transformed(&self, model_matrix: &Mat4) -> Self {
+        Self::from_points(
+            self.corners()
+                .map(|corner| (model_matrix * Vec4::new(corner.x, corner.y, corner.z, 1.0)).xyz()),
+        )
+    }
+
+    // Prueba contra los 6 planos del frustum en espacio de recorte (sin dividir por w, para
+    // que las esquinas detrás de la cámara no se inviertan). La caja sólo se descarta si
+    // las 8 esquinas quedan fuera del mismo plano; puede aceptar alguna caja que roza una
+    // arista del frustum, pero nunca descarta una visible
+    pub fn is_in_frustum(&self, view_projection: &Mat4) -> bool {
+        let clip = self
+            .corners()
+            .map(|corner| view_projection * Vec4::new(corner.x, corner.y, corner.z, 1.0));
+        let planes: [fn(&Vec4) -> bool; 6] = [
+            |c| c.x < -c.w,
+            |c| c.x > c.w,
+            |c| c.y < -c.w,
+            |c| c.y > c.w,
+            |c| c.z < -c.w,
+            |c| c.z > c.w,
+        ];
+        !planes.iter().any(|outside| clip.iter().all(outside))
+    }
+}
@@ -0,0 +1,214 @@
+use crate::camera::ProjectionConfig;
+use crate::framebuffer::Rect;
+use crate::scene::{
+    DEFAULT_AMBIENT_LIGHT, DEFAULT_ASTEROID_SEED, DEFAULT_NOISE_SEED, DEFAULT_RING_SEED,
+};
+use std::env;
+use std::str::FromStr;
+
+pub struct Args {
+    pub export_orbits: Option<String>,
+    pub time_start: u32,
+    pub time_end: u32,
+    pub time_step: u32,
+    pub skybox_cross: Option<String>,
+    pub pause_audio_in_background: bool,
+    pub music: String,
+    pub collision_sound: String,
+    pub system: Option<String>,
+    pub record: Option<String>,
+    pub replay: Option<String>,
+    pub adaptive_near: bool,
+    pub render_region: Option<Rect>,
+    pub seam_offset: f32,
+    pub parallel_bodies: bool,
+    pub antialias_orbits: bool,
+    pub ring_seed: u64,
+    pub asteroid_seed: u64,
+    pub noise_seed: i32,
+    pub skybox_exposure: f32,
+    pub ambient_light: f32,
+    pub planet_textures: Vec<(usize, String)>,
+    pub projection: ProjectionConfig,
+    pub screenshot: Option<String>,
+    pub time: u32,
+    pub width: usize,
+    pub height: usize,
+    pub fullscreen: bool,
+    pub ssaa: usize,
+    pub edge_aa: bool,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        let mut args = Args {
+            export_orbits: None,
+            time_start: 0,
+            time_end: 1000,
+            time_step: 10,
+            skybox_cross: None,
+            pause_audio_in_background: false,
+            music: "assets/audio".to_string(),
+            collision_sound: "assets/sfx/thud.wav".to_string(),
+            system: None,
+            record: None,
+            replay: None,
+            adaptive_near: false,
+            render_region: None,
+            seam_offset: 0.0,
+            parallel_bodies: false,
+            antialias_orbits: false,
+            ring_seed: DEFAULT_RING_SEED,
+            asteroid_seed: DEFAULT_ASTEROID_SEED,
+            noise_seed: DEFAULT_NOISE_SEED,
+            skybox_exposure: 1.0,
+            ambient_light: DEFAULT_AMBIENT_LIGHT,
+            planet_textures: Vec::new(),
+            projection: ProjectionConfig::default(),
+            screenshot: None,
+            time: 0,
+            width: 1000,
+            height: 800,
+            fullscreen: false,
+            ssaa: 1,
+            edge_aa: false,
+        };
+
+        let mut iter = env::args().skip(1);
+        while let Some(flag) = iter.next() {
+            match flag.as_str() {
+                "--export-orbits" => args.export_orbits = Some(next_value(&mut iter, &flag)?),
+                "--time-start" => args.time_start = parse_number(&mut iter, &flag)?,
+                "--time-end" => args.time_end = parse_number(&mut iter, &flag)?,
+                "--time-step" => args.time_step = parse_number(&mut iter, &flag)?,
+                "--skybox-cross" => args.skybox_cross = Some(next_value(&mut iter, &flag)?),
+                "--pause-audio-in-background" => args.pause_audio_in_background = true,
+                "--music" => args.music = next_value(&mut iter, &flag)?,
+                "--collision-sound" => args.collision_sound = next_value(&mut iter, &flag)?,
+                "--system" => args.system = Some(next_value(&mut iter, &flag)?),
+                "--record" => args.record = Some(next_value(&mut iter, &flag)?),
+                "--replay" => args.replay = Some(next_value(&mut iter, &flag)?),
+                "--adaptive-near" => args.adaptive_near = true,
+                "--render-region" => args.render_region = Some(parse_rect(&mut iter, &flag)?),
+                "--seam-offset" => args.seam_offset = parse_number(&mut iter, &flag)?,
+                "--parallel-bodies" => args.parallel_bodies = true,
+                "--antialias-orbits" => args.antialias_orbits = true,
+                "--ring-seed" => args.ring_seed = parse_number(&mut iter, &flag)?,
+                "--asteroid-seed" => args.asteroid_seed = parse_number(&mut iter, &flag)?,
+                "--noise-seed" => args.noise_seed = parse_number(&mut iter, &flag)?,
+                "--skybox-exposure" => args.skybox_exposure = parse_number(&mut iter, &flag)?,
+                "--ambient" => args.ambient_light = parse_number(&mut iter, &flag)?,
+                "--planet-texture" => args
+                    .planet_textures
+                    .push(parse_planet_texture(&mut iter, &flag)?),
+                "--fov" => args.projection.fov_deg = parse_number(&mut iter, &flag)?,
+                "--near" => args.projection.near = parse_number(&mut iter, &flag)?,
+                "--far" => args.projection.far = parse_number(&mut iter, &flag)?,
+                "--screenshot" => args.screenshot = Some(next_value(&mut iter, &flag)?),
+                "--time" => args.time = parse_number(&mut iter, &flag)?,
+                "--width" => args.width = parse_number(&mut iter, &flag)?,
+                "--height" => args.height = parse_number(&mut iter, &flag)?,
+                "--fullscreen" => args.fullscreen = true,
+                "--ssaa" => args.ssaa = parse_number(&mut iter, &flag)?,
+                "--edge-aa" => args.edge_aa = true,
+                _ => return Err(format!("Argumento desconocido: {}", flag)),
+            }
+        }
+
+        if args.time_step == 0 {
+            return Err("--time-step debe ser mayor que 0".to_string());
+        }
+        if args.time_end < args.time_start {
+            return Err("--time-end debe ser mayor o igual que --time-start".to_string());
+        }
+
+        if args.skybox_exposure.is_nan() || args.skybox_exposure < 0.0 {
+            return Err("--skybox-exposure no puede ser negativo".to_string());
+        }
+        if !(0.0..=1.0).contains(&args.ambient_light) {
+            return Err("--ambient debe estar entre 0 y 1".to_string());
+        }
+
+        let projection = args.projection;
+        if !(projection.fov_deg > 0.0 && projection.fov_deg < 180.0) {
+            return Err("--fov debe estar entre 0 y 180 grados".to_string());
+        }
+        if !(projection.near > 0.0 && projection.far > projection.near) {
+            return Err("Se necesita 0 < --near < --far".to_string());
+        }
+
+        // La ventana y el framebuffer comparten tamaño, así que update_with_buffer siempre
+        // recibe un buffer de las dimensiones de la ventana
+        if args.width == 0 || args.height == 0 {
+            return Err("--width y --height deben ser mayores que 0".to_string());
+        }
+        if let Some(region) = args.render_region {
+            if region.x + region.width > args.width || region.y + region.height > args.height {
+                return Err("--render-region no cabe en la ventana".to_string());
+            }
+        }
+
+        if !matches!(args.ssaa, 1 | 2 | 4) {
+            return Err("--ssaa debe ser 1, 2 o 4".to_string());
+        }
+
+        if args.record.is_some() && args.replay.is_some() {
+            return Err("--record y --replay no se pueden usar a la vez".to_string());
+        }
+
+        Ok(args)
+    }
+}
+
+fn next_value(iter: &mut impl Iterator<Item = String>, flag: &str) -> Result<String, String> {
+    iter.next()
+        .ok_or_else(|| format!("Falta el valor para {}", flag))
+}
+
+fn parse_number<T: FromStr>(
+    iter: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<T, String> {
+    let value = next_value(iter, flag)?;
+    value
+        .parse()
+        .map_err(|_| format!("Valor inválido para {}: {}", flag, value))
+}
+
+// Rectángulo en formato x,y,ancho,alto
+fn parse_rect(iter: &mut impl Iterator<Item = String>, flag: &str) -> Result<Rect, String> {
+    let value = next_value(iter, flag)?;
+    let parts: Vec<usize> = value
+        .split(',')
+        .map(|part| part.trim().parse())
+        .collect::<Result<_, _>>()
+        .map_err(|_| format!("Valor inválido para {}: {}", flag, value))?;
+    match parts[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Rect {
+            x,
+            y,
+            width,
+            height,
+        }),
+        _ => Err(format!("{} espera x,y,ancho,alto: {}", flag, value)),
+    }
+}
+
+// Textura de superficie para un planeta en formato índice=ruta (índice desde 1, como las
+// teclas de selección)
+fn parse_planet_texture(
+    iter: &mut impl Iterator<Item = String>,
+    flag: &str,
+) -> Result<(usize, String), String> {
+    let value = next_value(iter, flag)?;
+    let (index, path) = value
+        .split_once('=')
+        .ok_or_else(|| format!("{} espera planeta=ruta: {}", flag, value))?;
+    match index.trim().parse::<usize>() {
+        // El límite superior depende del sistema cargado y se comprueba después
+        Ok(number) if number >= 1 && !path.is_empty() => {
+            Ok((number - 1, path.to_string()))
+        }
+        _ => Err(format!("Valor inválido para {}: {}", flag, value)),
+    }
+}
@@ -0,0 +1,180 @@
+use crate::aabb::Aabb;
+use crate::framebuffer::Framebuffer;
+use crate::uniforms::Uniforms;
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::PI;
+
+// Tono medio del shader rocoso, para que no cambie de color al pasar de cuadrado a roca
+const ASTEROID_COLOR: u32 = 0x8C5434;
+const MIN_ASTEROID_SIZE: f32 = 0.1;
+const MAX_ASTEROID_SIZE: f32 = 0.4;
+const MAX_ASTEROID_HEIGHT: f32 = 1.0;
+
+// Un asteroide del cinturón: órbita circular con una pequeña desviación vertical
+pub struct Asteroid {
+    pub orbit_radius: f32,
+    pub phase: f32,
+    pub height: f32,
+    pub size: f32,
+    pub speed: f32,
+    pub spin: f32,
+}
+
+impl Asteroid {
+    pub fn position(&self, time: u32) -> Vec3 {
+        let angle = self.phase + time as f32 * self.speed;
+        Vec3::new(
+            self.orbit_radius * angle.cos(),
+            self.height,
+            self.orbit_radius * angle.sin(),
+        )
+    }
+
+    // Transformación de la instancia: posición, giro propio y tamaño
+    pub fn transform(&self, time: u32) -> Mat4 {
+        Mat4::new_translation(&self.position(time))
+            * Mat4::from_axis_angle(&Vec3::y_axis(), time as f32 * self.spin)
+            * Mat4::new_scaling(self.size)
+    }
+}
+
+// Cinturón de asteroides generado a partir de una semilla: la misma semilla produce
+// siempre la misma distribución, así que una disposición concreta se puede reproducir.
+// Guarda el radio exterior para poder acotarlo entero
+pub struct AsteroidBelt {
+    seed: u64,
+    outer: f32,
+    asteroids: Vec<Asteroid>,
+}
+
+impl AsteroidBelt {
+    pub fn generate(seed: u64, inner: f32, outer: f32, count: usize) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let asteroids = (0..count)
+            .map(|_| {
+                let orbit_radius = rng.gen_range(inner..outer);
+                Asteroid {
+                    orbit_radius,
+                    phase: rng.gen_range(0.0..2.0 * PI),
+                    height: rng.gen_range(-MAX_ASTEROID_HEIGHT..MAX_ASTEROID_HEIGHT),
+                    size: rng.gen_range(MIN_ASTEROID_SIZE..MAX_ASTEROID_SIZE),
+                    // Los más cercanos al sol giran un poco más rápido
+                    speed: 0.12 / orbit_radius * rng.gen_range(0.9..1.1),
+                    spin: rng.gen_range(-0.05..0.05),
+                }
+            })
+            .collect();
+        AsteroidBelt {
+            seed,
+            outer,
+            asteroids,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    // Semilla del siguiente cinturón al volver a sembrar: sale de la actual, así que una
+    // sesión grabada que la cambia se reproduce con los mismos cinturones
+    pub fn next_seed(&self) -> u64 {
+        StdRng::seed_from_u64(self.seed).gen()
+    }
+
+    pub fn asteroids(&self) -> &[Asteroid] {
+        &self.asteroids
+    }
+
+    // Caja que contiene todo el cinturón, para descartarlo de una vez cuando queda fuera
+    // de la vista
+    pub fn bounds(&self) -> Aabb {
+        let extent = self.outer + MAX_ASTEROID_SIZE;
+        let height = MAX_ASTEROID_HEIGHT + MAX_ASTEROID_SIZE;
+        Aabb {
+            min: Vec3::new(-extent, -height, -extent),
+            max: Vec3::new(extent, height, extent),
+        }
+    }
+}
+
+// Centro del asteroide en pantalla (x, y, z NDC) y el tamaño que ocupa en píxeles, o None
+// si queda detrás de la cámara o fuera del rango de profundidad
+pub fn project(asteroid: &Asteroid, uniforms: &Uniforms) -> Option<(Vec4, f32)> {
+    let world = asteroid.transform(uniforms.time) * Vec4::new(0.0, 0.0, 0.0, 1.0);
+    let clip = uniforms.projection_matrix * uniforms.view_matrix * world;
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    if ndc.z.abs() > 1.0 {
+        return None;
+    }
+    let focal = uniforms.projection_matrix[(1, 1)] * uniforms.viewport_matrix[(1, 1)].abs();
+    let screen = uniforms.viewport_matrix * ndc;
+    Some((
+        Vec4::new(screen.x, screen.y, ndc.z, 1.0),
+        asteroid.size * focal / clip.w,
+    ))
+}
+
+// Asteroide lejano como un cuadrado con prueba de profundidad, de lado 2 * half (al menos
+// un píxel)
+pub fn render_splat(framebuffer: &mut Framebuffer, screen: &Vec4, half: f32) {
+    framebuffer.set_current_color(ASTEROID_COLOR);
+    let half = half.max(0.5);
+
+    let x0 = (screen.x - half).max(0.0) as usize;
+    let y0 = (screen.y - half).max(0.0) as usize;
+    let x1 = (screen.x + half).min(framebuffer.width as f32);
+    let y1 = (screen.y + half).min(framebuffer.height as f32);
+    if x1 < 0.0 || y1 < 0.0 {
+        return;
+    }
+    for y in y0..(y1.ceil() as usize).min(framebuffer.height) {
+        for x in x0..(x1.ceil() as usize).min(framebuffer.width) {
+            framebuffer.point(x, y, screen.z);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn transforms(belt: &AsteroidBelt, time: u32) -> Vec<Mat4> {
+        belt.asteroids()
+            .iter()
+            .map(|asteroid| asteroid.transform(time))
+            .collect()
+    }
+
+    #[test]
+    fn same_seed_builds_the_same_belt() {
+        let first = AsteroidBelt::generate(42, 80.0, 95.0, 200);
+        let second = AsteroidBelt::generate(42, 80.0, 95.0, 200);
+        for time in [0, 1000] {
+            assert_eq!(transforms(&first, time), transforms(&second, time));
+        }
+
+        let other = AsteroidBelt::generate(43, 80.0, 95.0, 200);
+        assert_ne!(transforms(&first, 0), transforms(&other, 0));
+    }
+
+    #[test]
+    fn reseeding_follows_the_same_sequence() {
+        let sequence = || {
+            let mut belt = AsteroidBelt::generate(42, 80.0, 95.0, 10);
+            (0..5)
+                .map(|_| {
+                    belt = AsteroidBelt::generate(belt.next_seed(), 80.0, 95.0, 10);
+                    belt.seed()
+                })
+                .collect::<Vec<u64>>()
+        };
+        let first = sequence();
+        assert_eq!(first, sequence());
+        assert!(!first.contains(&42));
+    }
+}
@@ -0,0 +1,214 @@
+use rodio::{
+    source::{Buffered, Source},
+    Decoder, OutputStreamHandle, Sink,
+};
+use std::fs::{self, File};
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+const MUSIC_VOLUME: f32 = 0.2;
+const MAX_MUSIC_VOLUME: f32 = 1.0;
+// Atenuación con la distancia al sol (modelo de distancia inversa): volumen completo hasta
+// la distancia de referencia, luego ref / (ref + rolloff * (d - ref)), sin bajar del mínimo
+// para que la pista nunca desaparezca. El cambio se suaviza cada cuadro para evitar saltos.
+const SUN_REFERENCE_DISTANCE: f32 = 40.0;
+const SUN_ROLLOFF: f32 = 0.5;
+const SUN_MIN_GAIN: f32 = 0.35;
+const SUN_GAIN_SMOOTHING: f32 = 0.1;
+const EFFECT_VOLUME: f32 = 0.6;
+const CROSSFADE_DURATION: Duration = Duration::from_secs(1);
+const MUSIC_EXTENSIONS: [&str; 4] = ["wav", "mp3", "ogg", "flac"];
+
+// Pista que se está desvaneciendo durante un fundido cruzado
+struct FadeOut {
+    sink: Sink,
+    start_volume: f32,
+}
+
+// Lista de pistas de fondo. Al cambiar de pista la actual baja de volumen mientras la
+// nueva sube, y la anterior se descarta al terminar el fundido.
+pub struct MusicPlayer {
+    stream_handle: OutputStreamHandle,
+    tracks: Vec<PathBuf>,
+    current: usize,
+    sink: Sink,
+    fade_out: Option<FadeOut>,
+    fade_start: Option<Instant>,
+    volume: f32,
+    sun_gain: f32,
+    target_sun_gain: f32,
+}
+
+impl MusicPlayer {
+    // Con un archivo la lista es solo esa pista; con un directorio se cargan todas sus
+    // pistas en orden alfabético y se empieza por la primera
+    pub fn new(stream_handle: OutputStreamHandle, path: &str) -> Result<Self, String> {
+        let mut tracks: Vec<PathBuf> = if Path::new(path).is_file() {
+            vec![PathBuf::from(path)]
+        } else {
+            fs::read_dir(path)
+                .map_err(|err| format!("No se pudo leer {}: {}", path, err))?
+                .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                .filter(|path| {
+                    path.extension()
+                        .and_then(|ext| ext.to_str())
+                        .is_some_and(|ext| MUSIC_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+                })
+                .collect()
+        };
+        tracks.sort();
+
+        let first = tracks
+            .first()
+            .ok_or_else(|| format!("No hay pistas de música en {}", path))?;
+        let sink = start_track(&stream_handle, first)?;
+        sink.set_volume(MUSIC_VOLUME);
+
+        Ok(MusicPlayer {
+            stream_handle,
+            tracks,
+            current: 0,
+            sink,
+            fade_out: None,
+            fade_start: None,
+            volume: MUSIC_VOLUME,
+            sun_gain: 1.0,
+            target_sun_gain: 1.0,
+        })
+    }
+
+    pub fn volume(&self) -> f32 {
+        self.volume
+    }
+
+    // Volumen elegido por el usuario; se aplica en el siguiente update()
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, MAX_MUSIC_VOLUME);
+    }
+
+    // Distancia de la cámara al sol, de la que sale la ganancia que update() va alcanzando
+    pub fn set_sun_distance(&mut self, distance: f32) {
+        self.target_sun_gain = sun_gain(distance);
+    }
+
+    pub fn track_name(&self) -> String {
+        self.tracks[self.current]
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    }
+
+    // Pasa a la siguiente pista con un fundido cruzado. Si ya había uno en curso, la pista
+    // que se desvanecía se corta y la que entraba pasa a desvanecerse desde su volumen actual.
+    pub fn next_track(&mut self) -> Result<(), String> {
+        let next = (self.current + 1) % self.tracks.len();
+        let sink = start_track(&self.stream_handle, &self.tracks[next])?;
+        sink.set_volume(0.0);
+        if self.sink.is_paused() {
+            sink.pause();
+        }
+
+        if let Some(previous) = self.fade_out.take() {
+            previous.sink.stop();
+        }
+        let outgoing = std::mem::replace(&mut self.sink, sink);
+        self.fade_out = Some(FadeOut {
+            start_volume: outgoing.volume(),
+            sink: outgoing,
+        });
+        self.fade_start = Some(Instant::now());
+        self.current = next;
+        Ok(())
+    }
+
+    // Avanza el fundido cruzado y la ganancia por distancia al sol; se llama una vez por cuadro
+    pub fn update(&mut self) {
+        self.sun_gain += (self.target_sun_gain - self.sun_gain) * SUN_GAIN_SMOOTHING;
+        let volume = self.volume * self.sun_gain;
+
+        let start = match self.fade_start {
+            Some(start) => start,
+            None => {
+                self.sink.set_volume(volume);
+                return;
+            }
+        };
+
+        let t = (start.elapsed().as_secs_f32() / CROSSFADE_DURATION.as_secs_f32()).min(1.0);
+        self.sink.set_volume(volume * t);
+        if let Some(fade_out) = &self.fade_out {
+            fade_out.sink.set_volume(fade_out.start_volume * (1.0 - t));
+        }
+
+        if t >= 1.0 {
+            if let Some(fade_out) = self.fade_out.take() {
+                fade_out.sink.stop();
+            }
+            self.fade_start = None;
+        }
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.sink.is_paused()
+    }
+
+    pub fn pause(&self) {
+        self.sink.pause();
+        if let Some(fade_out) = &self.fade_out {
+            fade_out.sink.pause();
+        }
+    }
+
+    pub fn play(&self) {
+        self.sink.play();
+        if let Some(fade_out) = &self.fade_out {
+            fade_out.sink.play();
+        }
+    }
+}
+
+// Efecto corto que se decodifica una sola vez y se reproduce en su propio sink, aparte de
+// la música, para no interrumpirla ni depender de su volumen
+pub struct SoundEffect {
+    sink: Sink,
+    source: Buffered<Decoder<BufReader<File>>>,
+}
+
+impl SoundEffect {
+    pub fn new(stream_handle: &OutputStreamHandle, path: &str) -> Result<Self, String> {
+        let sink = Sink::try_new(stream_handle)
+            .map_err(|err| format!("No se pudo crear el sink de audio: {}", err))?;
+        let file = File::open(path).map_err(|err| format!("No se pudo abrir {}: {}", path, err))?;
+        let source = Decoder::new(BufReader::new(file))
+            .map_err(|err| format!("No se pudo decodificar {}: {}", path, err))?
+            .buffered();
+        sink.set_volume(EFFECT_VOLUME);
+        Ok(SoundEffect { sink, source })
+    }
+
+    // Encola una copia del efecto ya decodificado
+    pub fn play(&self) {
+        self.sink.append(self.source.clone());
+    }
+}
+
+fn sun_gain(distance: f32) -> f32 {
+    if distance <= SUN_REFERENCE_DISTANCE {
+        return 1.0;
+    }
+    let gain = SUN_REFERENCE_DISTANCE
+        / (SUN_REFERENCE_DISTANCE + SUN_ROLLOFF * (distance - SUN_REFERENCE_DISTANCE));
+    gain.max(SUN_MIN_GAIN)
+}
+
+fn start_track(stream_handle: &OutputStreamHandle, path: &Path) -> Result<Sink, String> {
+    let sink = Sink::try_new(stream_handle)
+        .map_err(|err| format!("No se pudo crear el sink de audio: {}", err))?;
+    let file = File::open(path)
+        .map_err(|err| format!("No se pudo abrir {}: {}", path.display(), err))?;
+    let source = Decoder::new(BufReader::new(file))
+        .map_err(|err| format!("No se pudo decodificar {}: {}", path.display(), err))?;
+    sink.append(source.repeat_infinite());
+    Ok(sink)
+}
@@ -1,11 +1,52 @@
-use nalgebra_glm::Vec3;
+use nalgebra_glm::{look_at, Mat4, Vec3};
 use std::f32::consts::PI;
 
+// Límites de la distancia entre `eye` y `center` al hacer zoom
+const MIN_ZOOM_DISTANCE: f32 = 1.0;
+const MAX_ZOOM_DISTANCE: f32 = 800.0;
+
+// Parámetros de la proyección en perspectiva: campo de visión vertical en grados y
+// distancias de los planos cercano y lejano
+#[derive(Clone, Copy, Debug)]
+pub struct ProjectionConfig {
+    pub fov_deg: f32,
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Default for ProjectionConfig {
+    fn default() -> Self {
+        ProjectionConfig {
+            fov_deg: 75.0,
+            near: 0.1,
+            far: 1000.0,
+        }
+    }
+}
+
+// Orbit gira alrededor de `center`; Free mueve la cámara sobre sus propios ejes
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum CameraMode {
+    Orbit,
+    Free,
+}
+
+impl CameraMode {
+    pub fn toggle(self) -> Self {
+        match self {
+            CameraMode::Orbit => CameraMode::Free,
+            CameraMode::Free => CameraMode::Orbit,
+        }
+    }
+}
+
 pub struct Camera {
     pub eye: Vec3,
     pub center: Vec3,
     pub up: Vec3,
     pub has_changed: bool,
+    // Matriz de vista del último cálculo; vale mientras `has_changed` sea falso
+    view: Mat4,
 }
 
 impl Camera {
@@ -15,7 +56,32 @@ impl Camera {
             center,
             up,
             has_changed: true,
+            view: Mat4::identity(),
+        }
+    }
+
+    // Matriz de vista. Solo se recalcula si la cámara cambió desde la última llamada, y
+    // entonces se limpia `has_changed`
+    pub fn view_matrix(&mut self) -> Mat4 {
+        if self.has_changed {
+            self.view = self.current_view_matrix();
+            self.has_changed = false;
         }
+        self.view
+    }
+
+    // Matriz de vista calculada en el momento, sin pasar por la caché, para quien solo
+    // tiene una referencia compartida a la cámara
+    pub fn current_view_matrix(&self) -> Mat4 {
+        look_at(&self.eye, &self.center, &self.up)
+    }
+
+    // Vuelve a un encuadre dado, descartando la orientación acumulada
+    pub fn reset(&mut self, eye: Vec3, center: Vec3, up: Vec3) {
+        self.eye = eye;
+        self.center = center;
+        self.up = up;
+        self.has_changed = true;
     }
 
     // Rotación en órbita
@@ -43,24 +109,86 @@ impl Camera {
         self.has_changed = true;
     }
 
-    // Movimiento vertical en el eje Y
-    pub fn move_vertical(&mut self, delta: f32) {
-        self.eye.y += delta;
-        self.center.y += delta;
-        self.has_changed = true;
-    }
-
-    // Movimiento en el plano horizontal (XZ)
+    // Traslada `eye` y `center` juntos. Solo mueve la cámara: `up` se conserva para no
+    // deshacer la orientación que hayan dejado otras operaciones
     pub fn move_center(&mut self, movement: Vec3) {
         self.eye += movement;
         self.center += movement;
-        self.up = Vec3::new(0.0, 1.0, 0.0); 
+        self.has_changed = true;
     }
 
-    // Zoom hacia adelante y atrás
+    // Zoom hacia adelante y atrás. La distancia al centro se limita a
+    // [MIN_ZOOM_DISTANCE, MAX_ZOOM_DISTANCE]; si ya estaba fuera, no se aleja más del rango
     pub fn zoom(&mut self, delta: f32) {
-        let direction = (self.center - self.eye).normalize();
-        self.eye += direction * delta;
+        let offset = self.center - self.eye;
+        let distance = offset.magnitude();
+        let min = MIN_ZOOM_DISTANCE.min(distance);
+        let max = MAX_ZOOM_DISTANCE.max(distance);
+        let new_distance = (distance - delta).clamp(min, max);
+        self.eye = self.center - offset.normalize() * new_distance;
+        self.has_changed = true;
+    }
+
+    // Desplazamiento en el mundo equivalente a moverse sobre los ejes de la cámara
+    // (adelante hacia `center`, derecha y arriba de la vista)
+    pub fn fly_offset(&self, forward: f32, right: f32, up: f32) -> Vec3 {
+        let forward_dir = (self.center - self.eye).normalize();
+        let right_dir = forward_dir.cross(&self.up).normalize();
+        let up_dir = right_dir.cross(&forward_dir);
+        forward_dir * forward + right_dir * right + up_dir * up
+    }
+
+    // Gira la dirección de vista desde la posición actual (el ojo no se mueve). El pitch se
+    // limita igual que en `orbit` para no dar la vuelta por los polos.
+    pub fn yaw_pitch(&mut self, delta_yaw: f32, delta_pitch: f32) {
+        let direction = self.center - self.eye;
+        let distance = direction.magnitude();
+
+        let yaw = direction.z.atan2(direction.x) + delta_yaw;
+        let pitch = ((direction.y / distance).asin() + delta_pitch)
+            .clamp(-PI / 2.0 + 0.1, PI / 2.0 - 0.1);
+
+        self.center = self.eye
+            + Vec3::new(
+                distance * yaw.cos() * pitch.cos(),
+                distance * pitch.sin(),
+                distance * yaw.sin() * pitch.cos(),
+            );
         self.has_changed = true;
     }
 }
+
+// Vuelo animado de la cámara hacia un objetivo que puede seguir moviéndose (un planeta en
+// órbita). `from` guarda el ojo y el centro al empezar; `to` es la posición final del ojo
+// relativa al objetivo, así el encuadre final sigue mirándolo aunque se haya desplazado
+pub struct CameraAnimation {
+    pub from: (Vec3, Vec3),
+    pub to: Vec3,
+    pub t: f32,
+}
+
+impl CameraAnimation {
+    pub fn new(camera: &Camera, to: Vec3) -> Self {
+        CameraAnimation {
+            from: (camera.eye, camera.center),
+            to,
+            t: 0.0,
+        }
+    }
+
+    // Avanza la animación la fracción `step` del recorrido y coloca la cámara entre el
+    // inicio y el encuadre sobre `target`, con aceleración y frenado suaves.
+    // Devuelve true cuando termina.
+    pub fn apply(&mut self, camera: &mut Camera, target: &Vec3, step: f32) -> bool {
+        self.t = (self.t + step).min(1.0);
+        let eased = self.t * self.t * (3.0 - 2.0 * self.t);
+
+        let (from_eye, from_center) = self.from;
+        camera.eye = from_eye.lerp(&(target + self.to), eased);
+        camera.center = from_center.lerp(target, eased);
+        camera.up = Vec3::new(0.0, 1.0, 0.0);
+        camera.has_changed = true;
+
+        self.t >= 1.0
+    }
+}
@@ -1,6 +1,49 @@
+use nalgebra_glm::Vec3;
 use std::fmt;
 
-#[derive(Debug, Clone, Copy)]
+// Codificación gamma de los shaders que trabajan en color lineal (Vec3), después del mapeo
+// de tonos elegido con T
+pub const GAMMA: f32 = 2.2;
+
+// Valor de canal a 8 bits: redondea y satura en [0, 255] en lugar de truncar, así los
+// productos por factores mayores que 1 se quedan en blanco y no se dan la vuelta
+fn to_channel(value: f32) -> u8 {
+    value.round().clamp(0.0, 255.0) as u8
+}
+
+// Aproximación de la curva ACES de Krzysztof Narkowicz
+pub fn aces(c: f32) -> f32 {
+    ((c * (2.51 * c + 0.03)) / (c * (2.43 * c + 0.59) + 0.14)).clamp(0.0, 1.0)
+}
+
+// Operadores de mapeo de tonos: Color::from_linear los aplica al color lineal de los
+// shaders antes de cuantizarlo, así las altas luces se comprimen en lugar de saturar
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ToneMap {
+    Clamp,
+    Reinhard,
+    Aces,
+}
+
+impl ToneMap {
+    pub fn next(self) -> Self {
+        match self {
+            ToneMap::Clamp => ToneMap::Reinhard,
+            ToneMap::Reinhard => ToneMap::Aces,
+            ToneMap::Aces => ToneMap::Clamp,
+        }
+    }
+
+    pub fn map(self, color: Vec3) -> Vec3 {
+        match self {
+            ToneMap::Clamp => color.map(|c| c.clamp(0.0, 1.0)),
+            ToneMap::Reinhard => color.map(|c| c / (1.0 + c)),
+            ToneMap::Aces => color.map(aces),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub r: u8,
     pub g: u8,
@@ -17,9 +60,46 @@ impl Color {
         Color { r: 0, g: 0, b: 0, a: 0}
     }
 
-    pub fn to_hex(&self) -> u32 {
+    pub fn to_hex(self) -> u32 {
         ((self.r as u32) << 16) | ((self.g as u32) << 8) | (self.b as u32)
     }
+
+    // Inverso de to_hex: 0xRRGGBB, con el alfa (si lo hay) en el byte alto
+    pub fn from_hex(hex: u32) -> Self {
+        Color::new(
+            (hex >> 16) as u8,
+            (hex >> 8) as u8,
+            hex as u8,
+            (hex >> 24) as u8,
+        )
+    }
+
+    // Color escrito como "#RRGGBB", para leerlo de archivos de configuración
+    pub fn from_hex_str(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Color inválido: {} (se esperaba #RRGGBB)", text);
+        let digits = text
+            .trim()
+            .strip_prefix('#')
+            .filter(|digits| digits.len() == 6 && digits.chars().all(|c| c.is_ascii_hexdigit()))
+            .ok_or_else(invalid)?;
+        u32::from_str_radix(digits, 16)
+            .map(Color::from_hex)
+            .map_err(|_| invalid())
+    }
+
+    // Color lineal a 8 bits: comprime las altas luces con el operador de tonos mientras el
+    // color sigue en coma flotante y luego codifica con gamma
+    pub fn from_linear(color: Vec3, tone_map: ToneMap) -> Self {
+        let mapped = tone_map.map(color);
+        let channel = |c: f32| to_channel(c.clamp(0.0, 1.0).powf(1.0 / GAMMA) * 255.0);
+        Color::new(channel(mapped.x), channel(mapped.y), channel(mapped.z), 0)
+    }
+
+    // Color de 8 bits a lineal, para escribir las constantes de los shaders HDR
+    pub fn to_linear(self) -> Vec3 {
+        let channel = |c: u8| (c as f32 / 255.0).powf(GAMMA);
+        Vec3::new(channel(self.r), channel(self.g), channel(self.b))
+    }
     
 
     // Linear interpolation between two colors
@@ -65,10 +145,10 @@ impl Mul<f32> for Color {
 
     fn mul(self, scalar: f32) -> Color {
         Color {
-            r: (self.r as f32 * scalar).clamp(0.0, 255.0) as u8,
-            g: (self.g as f32 * scalar).clamp(0.0, 255.0) as u8,
-            b: (self.b as f32 * scalar).clamp(0.0, 255.0) as u8,
-            a: (self.a as f32 * scalar).clamp(0.0, 255.0) as u8,
+            r: to_channel(self.r as f32 * scalar),
+            g: to_channel(self.g as f32 * scalar),
+            b: to_channel(self.b as f32 * scalar),
+            a: to_channel(self.a as f32 * scalar),
         }
     }
 }
@@ -77,4 +157,82 @@ impl fmt::Display for Color {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "Color(r: {}, g: {}, b: {})", self.r, self.g, self.b)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn from_hex_inverts_to_hex() {
+        // to_hex descarta el alfa, así que la ida y vuelta se prueba con alfa 0
+        for r in (0..=255).step_by(15) {
+            for g in (0..=255).step_by(17) {
+                for b in [0, 1, 127, 128, 254, 255] {
+                    let color = Color::new(r, g, b, 0);
+                    assert_eq!(Color::from_hex(color.to_hex()), color);
+                }
+            }
+        }
+        assert_eq!(Color::from_hex(0x80FF_0000).a, 0x80);
+    }
+
+    #[test]
+    fn from_hex_str_parses_rrggbb_and_rejects_the_rest() {
+        assert_eq!(Color::from_hex_str("#1a2B3c"), Ok(Color::new(0x1A, 0x2B, 0x3C, 0)));
+        assert_eq!(Color::from_hex_str(" #FFFFFF "), Ok(Color::new(255, 255, 255, 0)));
+        for bad in ["", "#", "1a2b3c", "#1a2b3", "#1a2b3c4", "#1g2b3c", "#+1a2b3"] {
+            assert!(Color::from_hex_str(bad).is_err(), "{:?}", bad);
+        }
+    }
+
+    #[test]
+    fn from_linear_clamp_round_trips_to_linear() {
+        assert_eq!(Color::from_linear(Vec3::zeros(), ToneMap::Clamp), Color::black());
+        assert_eq!(
+            Color::from_linear(Vec3::repeat(1.0), ToneMap::Clamp),
+            Color::new(255, 255, 255, 0)
+        );
+        let color = Color::new(200, 100, 30, 0);
+        assert_eq!(Color::from_linear(color.to_linear(), ToneMap::Clamp), color);
+    }
+
+    #[test]
+    fn from_linear_keeps_detail_above_one() {
+        // Con Reinhard y ACES las altas luces siguen distinguiéndose en lugar de saturar
+        for tone_map in [ToneMap::Reinhard, ToneMap::Aces] {
+            let mut previous = 0;
+            for value in [1.0, 2.0, 4.0] {
+                let channel = Color::from_linear(Vec3::repeat(value), tone_map).r;
+                assert!(channel > previous, "{:?} no crece en {}", tone_map, value);
+                assert!(channel < 255, "{:?} satura en {}", tone_map, value);
+                previous = channel;
+            }
+        }
+    }
+
+    #[test]
+    fn tone_maps_keep_black_and_are_monotonic() {
+        for tone_map in [ToneMap::Clamp, ToneMap::Reinhard, ToneMap::Aces] {
+            assert_eq!(tone_map.map(Vec3::zeros()), Vec3::zeros(), "{:?}", tone_map);
+            let mut previous = 0.0;
+            for step in 1..=400 {
+                let mapped = tone_map.map(Vec3::repeat(step as f32 * 0.025)).x;
+                assert!(mapped >= previous, "{:?} baja en {}", tone_map, step);
+                assert!(mapped <= 1.0, "{:?} pasa de 1 en {}", tone_map, step);
+                previous = mapped;
+            }
+        }
+    }
+
+    #[test]
+    fn mul_and_add_saturate_at_white() {
+        let gray = Color::new(200, 200, 200, 0);
+        assert_eq!(gray * 2.0, Color::new(255, 255, 255, 0));
+        assert_eq!(gray * -1.0, Color::black());
+        assert_eq!(gray * 0.5, Color::new(100, 100, 100, 0));
+
+        let sum = Color::new(200, 100, 10, 0) + Color::new(100, 100, 10, 0);
+        assert_eq!(sum, Color::new(255, 200, 20, 0));
+    }
+}
@@ -0,0 +1,166 @@
+use crate::color::Color;
+use crate::framebuffer::{BlendMode, Framebuffer};
+use crate::scene::{
+    ellipse_point, COMET_ECCENTRICITY, COMET_INCLINATION, COMET_MEAN_MOTION, COMET_PHASE,
+    COMET_RADIUS, COMET_SEMI_MAJOR,
+};
+use crate::uniforms::Uniforms;
+use nalgebra_glm::{Vec3, Vec4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::PI;
+
+const NUCLEUS_COLOR: Color = Color::new(225, 230, 240, 0);
+// Luz de la cara nocturna del núcleo, para que no desaparezca contra el fondo
+const NUCLEUS_AMBIENT: f32 = 0.25;
+const COMA_COLOR: u32 = 0xBFE4FF;
+// Radio de la coma (el halo alrededor del núcleo) como múltiplo del radio del núcleo
+const COMA_SCALE: f32 = 3.0;
+const COMA_INTENSITY: f32 = 0.6;
+
+const TAIL_COLOR: u32 = 0x8FD0FF;
+const TAIL_PARTICLES: usize = 400;
+const TAIL_SEED: u64 = 7;
+// La cola mide TAIL_LENGTH_SCALE / distancia al sol, hasta TAIL_MAX_LENGTH: crece al
+// acercarse al sol
+const TAIL_LENGTH_SCALE: f32 = 300.0;
+const TAIL_MAX_LENGTH: f32 = 30.0;
+// Apertura de la cola: separación lateral máxima por unidad de longitud
+const TAIL_SPREAD: f32 = 0.12;
+// Fracción de la cola que recorre cada partícula por cuadro
+const TAIL_FLOW_SPEED: f32 = 0.004;
+const TAIL_PARTICLE_SIZE: f32 = 0.2;
+const TAIL_INTENSITY: f32 = 0.7;
+
+// Posición del cometa: se resuelve la ecuación de Kepler (M = E - e sen E) por Newton para
+// pasar de la anomalía media, que crece uniforme con el tiempo, a la excéntrica
+pub fn comet_position(time: u32) -> Vec3 {
+    let mean_anomaly = (COMET_PHASE + time as f32 * COMET_MEAN_MOTION).rem_euclid(2.0 * PI);
+    let e = COMET_ECCENTRICITY;
+    let mut eccentric_anomaly = mean_anomaly + e * mean_anomaly.sin();
+    for _ in 0..10 {
+        let error = eccentric_anomaly - e * eccentric_anomaly.sin() - mean_anomaly;
+        eccentric_anomaly -= error / (1.0 - e * eccentric_anomaly.cos());
+    }
+    ellipse_point(COMET_SEMI_MAJOR, e, COMET_INCLINATION, eccentric_anomaly)
+}
+
+// Proyección a pantalla: (x, y, z NDC) y el radio en píxeles de una esfera de radio
+// `radius` en ese punto, o None si queda detrás de la cámara o fuera del rango de profundidad
+fn project(point: &Vec3, radius: f32, uniforms: &Uniforms) -> Option<(Vec3, f32)> {
+    let clip = uniforms.projection_matrix
+        * uniforms.view_matrix
+        * Vec4::new(point.x, point.y, point.z, 1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    if ndc.z.abs() > 1.0 {
+        return None;
+    }
+    let screen = uniforms.viewport_matrix * ndc;
+    let focal = uniforms.projection_matrix[(1, 1)] * uniforms.viewport_matrix[(1, 1)].abs();
+    Some((
+        Vec3::new(screen.x, screen.y, ndc.z),
+        radius * focal / clip.w,
+    ))
+}
+
+// Recorre los píxeles (dentro de una pantalla de width x height) del disco de radio `radius`
+// centrado en `center`, pasando cada uno con su posición relativa al centro en radios
+fn for_each_disc_pixel(
+    (width, height): (usize, usize),
+    center: &Vec3,
+    radius: f32,
+    mut visit: impl FnMut(usize, usize, f32, f32),
+) {
+    let x0 = (center.x - radius).floor().max(0.0) as usize;
+    let y0 = (center.y - radius).floor().max(0.0) as usize;
+    let x1 = ((center.x + radius).ceil().max(0.0) as usize).min(width);
+    let y1 = ((center.y + radius).ceil().max(0.0) as usize).min(height);
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let dx = (x as f32 + 0.5 - center.x) / radius;
+            let dy = (y as f32 + 0.5 - center.y) / radius;
+            if dx * dx + dy * dy <= 1.0 {
+                visit(x, y, dx, dy);
+            }
+        }
+    }
+}
+
+// Dibuja el cometa: el núcleo como una esfera pequeña iluminada por el sol, con prueba y
+// escritura de profundidad, y encima la coma y la cola en modo aditivo, que respetan el
+// z-buffer pero no escriben en él. La cola apunta en dirección contraria al sol y sus
+// partículas fluyen hacia fuera, apagándose hacia la punta.
+pub fn render_comet(
+    framebuffer: &mut Framebuffer,
+    position: &Vec3,
+    sun_pos: &Vec3,
+    uniforms: &Uniforms,
+) {
+    let away_from_sun = position - sun_pos;
+    let distance = away_from_sun.magnitude();
+    let tail_direction = away_from_sun / distance;
+    let size = (framebuffer.width, framebuffer.height);
+
+    if let Some((center, radius)) = project(position, COMET_RADIUS, uniforms) {
+        let radius = radius.max(1.0);
+        // La luz se pasa al espacio de la cámara, donde el disco en pantalla es la esfera
+        let light = uniforms.view_matrix
+            * Vec4::new(-tail_direction.x, -tail_direction.y, -tail_direction.z, 0.0);
+        let light = Vec3::new(light.x, light.y, light.z);
+        for_each_disc_pixel(size, &center, radius, |x, y, dx, dy| {
+            let normal = Vec3::new(dx, -dy, (1.0 - dx * dx - dy * dy).max(0.0).sqrt());
+            let diffuse = normal.dot(&light).max(0.0);
+            let shade = NUCLEUS_AMBIENT + (1.0 - NUCLEUS_AMBIENT) * diffuse;
+            framebuffer.set_current_color((NUCLEUS_COLOR * shade).to_hex());
+            framebuffer.point(x, y, center.z);
+        });
+
+        framebuffer.set_blend_mode(BlendMode::Additive);
+        framebuffer.set_current_color(COMA_COLOR);
+        for_each_disc_pixel(size, &center, radius * COMA_SCALE, |x, y, dx, dy| {
+            let falloff = 1.0 - (dx * dx + dy * dy).sqrt();
+            framebuffer.set_current_alpha(falloff * falloff * COMA_INTENSITY);
+            framebuffer.point(x, y, center.z);
+        });
+    }
+
+    // Base perpendicular a la cola para abrir las partículas a su alrededor
+    let helper = if tail_direction.y.abs() < 0.9 {
+        Vec3::new(0.0, 1.0, 0.0)
+    } else {
+        Vec3::new(1.0, 0.0, 0.0)
+    };
+    let side = tail_direction.cross(&helper).normalize();
+    let up = tail_direction.cross(&side);
+    let length = (TAIL_LENGTH_SCALE / distance).min(TAIL_MAX_LENGTH);
+
+    // Cada partícula tiene su fase, ángulo y apertura fijos (misma semilla en cada cuadro);
+    // solo avanza a lo largo de la cola con el tiempo
+    let mut rng = StdRng::seed_from_u64(TAIL_SEED);
+    framebuffer.set_blend_mode(BlendMode::Additive);
+    framebuffer.set_current_color(TAIL_COLOR);
+    for _ in 0..TAIL_PARTICLES {
+        let phase: f32 = rng.gen_range(0.0..1.0);
+        let angle: f32 = rng.gen_range(0.0..2.0 * PI);
+        let spread: f32 = rng.gen_range(0.0..1.0);
+        let along = (phase + uniforms.time as f32 * TAIL_FLOW_SPEED).fract();
+
+        let offset =
+            (side * angle.cos() + up * angle.sin()) * spread * TAIL_SPREAD * length * along;
+        let point = position + tail_direction * length * along + offset;
+        let particle_size = TAIL_PARTICLE_SIZE * (1.0 + 2.0 * along);
+        let Some((center, radius)) = project(&point, particle_size, uniforms) else {
+            continue;
+        };
+        let brightness = (1.0 - along).powf(1.5) * TAIL_INTENSITY;
+        for_each_disc_pixel(size, &center, radius.max(1.0), |x, y, dx, dy| {
+            let falloff = 1.0 - (dx * dx + dy * dy).sqrt();
+            framebuffer.set_current_alpha(brightness * falloff);
+            framebuffer.point(x, y, center.z);
+        });
+    }
+    framebuffer.set_blend_mode(BlendMode::Opaque);
+}
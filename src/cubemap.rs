@@ -0,0 +1,184 @@
+use crate::color::Color;
+use image::{imageops, RgbaImage};
+use nalgebra_glm::Vec3;
+
+// Caras en el orden +X, -X, +Y, -Y, +Z, -Z
+const POSITIVE_X: usize = 0;
+const NEGATIVE_X: usize = 1;
+const POSITIVE_Y: usize = 2;
+const NEGATIVE_Y: usize = 3;
+const POSITIVE_Z: usize = 4;
+const NEGATIVE_Z: usize = 5;
+
+pub struct Cubemap {
+    faces: Vec<RgbaImage>,
+    size: u32,
+}
+
+impl Cubemap {
+    // Carga un cubemap desde una sola imagen en cruz. La orientación se detecta por la
+    // relación de aspecto:
+    //
+    //   horizontal (4x3)         vertical (3x4)
+    //       [+Y]                     [+Y]
+    //   [-X][+Z][+X][-Z]         [-X][+Z][+X]
+    //       [-Y]                     [-Y]
+    //                                [-Z]  (girada 180°)
+    pub fn from_cross(path: &str) -> Result<Self, String> {
+        let image = image::open(path)
+            .map_err(|err| format!("No se pudo cargar el cubemap {}: {}", path, err))?
+            .to_rgba8();
+        Self::from_cross_image(&image)
+    }
+
+    pub fn from_cross_image(image: &RgbaImage) -> Result<Self, String> {
+        let (width, height) = image.dimensions();
+
+        let horizontal = width % 4 == 0 && height % 3 == 0 && width / 4 == height / 3;
+        let vertical = width % 3 == 0 && height % 4 == 0 && width / 3 == height / 4;
+
+        // (columna, fila) de cada cara dentro de la cruz
+        let layout = if horizontal {
+            [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)]
+        } else if vertical {
+            [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)]
+        } else {
+            return Err(format!(
+                "Dimensiones de cruz inválidas {}x{}: se esperaba 4x3 o 3x4 caras cuadradas",
+                width, height
+            ));
+        };
+
+        let size = if horizontal { width / 4 } else { width / 3 };
+        // Una imagen vacía cumple las dos proporciones, pero sample necesita al menos un píxel
+        if size == 0 {
+            return Err("La cruz del cubemap está vacía (0x0)".to_string());
+        }
+        let faces = layout
+            .iter()
+            .enumerate()
+            .map(|(face, &(column, row))| {
+                let tile = imageops::crop_imm(image, column * size, row * size, size, size);
+                if vertical && face == NEGATIVE_Z {
+                    imageops::rotate180(&*tile)
+                } else {
+                    tile.to_image()
+                }
+            })
+            .collect();
+
+        Ok(Cubemap { faces, size })
+    }
+
+    // Devuelve el color en la dirección dada, eligiendo la cara por el eje dominante
+    pub fn sample(&self, direction: &Vec3) -> Color {
+        let (face, u, v) = face_uv(direction);
+        let x = ((u * self.size as f32) as u32).min(self.size - 1);
+        let y = ((v * self.size as f32) as u32).min(self.size - 1);
+        let pixel = self.faces[face].get_pixel(x, y);
+        Color::new(pixel[0], pixel[1], pixel[2], pixel[3])
+    }
+}
+
+fn face_uv(direction: &Vec3) -> (usize, f32, f32) {
+    let (x, y, z) = (direction.x, direction.y, direction.z);
+    let (ax, ay, az) = (x.abs(), y.abs(), z.abs());
+
+    let (face, sc, tc, ma) = if ax >= ay && ax >= az {
+        if x > 0.0 {
+            (POSITIVE_X, -z, -y, ax)
+        } else {
+            (NEGATIVE_X, z, -y, ax)
+        }
+    } else if ay >= az {
+        if y > 0.0 {
+            (POSITIVE_Y, x, z, ay)
+        } else {
+            (NEGATIVE_Y, x, -z, ay)
+        }
+    } else if z > 0.0 {
+        (POSITIVE_Z, x, -y, az)
+    } else {
+        (NEGATIVE_Z, -x, -y, az)
+    };
+
+    (face, (sc / ma + 1.0) * 0.5, (tc / ma + 1.0) * 0.5)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    const SIZE: u32 = 2;
+    const MARKER: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+    // Cruz sintética: cada cara de un color (10 * índice) con la esquina superior
+    // izquierda marcada en blanco, tal como se vería en la imagen de origen
+    fn cross(columns: u32, rows: u32, layout: &[(u32, u32); 6]) -> RgbaImage {
+        let mut image = RgbaImage::new(columns * SIZE, rows * SIZE);
+        for (face, &(column, row)) in layout.iter().enumerate() {
+            for y in 0..SIZE {
+                for x in 0..SIZE {
+                    let value = 10 * face as u8 + 10;
+                    image.put_pixel(column * SIZE + x, row * SIZE + y, Rgba([value, 0, 0, 255]));
+                }
+            }
+            image.put_pixel(column * SIZE, row * SIZE, MARKER);
+        }
+        image
+    }
+
+    fn assert_faces(cubemap: &Cubemap, rotated_negative_z: bool) {
+        assert_eq!(cubemap.size, SIZE);
+        for (face, tile) in cubemap.faces.iter().enumerate() {
+            let (marker, other) = if rotated_negative_z && face == NEGATIVE_Z {
+                ((SIZE - 1, SIZE - 1), (0, 0))
+            } else {
+                ((0, 0), (SIZE - 1, SIZE - 1))
+            };
+            assert_eq!(*tile.get_pixel(marker.0, marker.1), MARKER, "cara {}", face);
+            assert_eq!(tile.get_pixel(other.0, other.1)[0], 10 * face as u8 + 10, "cara {}", face);
+        }
+    }
+
+    #[test]
+    fn horizontal_cross_is_sliced_into_six_faces() {
+        let layout = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)];
+        let cubemap = Cubemap::from_cross_image(&cross(4, 3, &layout)).unwrap();
+        assert_faces(&cubemap, false);
+    }
+
+    #[test]
+    fn vertical_cross_rotates_the_negative_z_face() {
+        let layout = [(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (1, 3)];
+        let cubemap = Cubemap::from_cross_image(&cross(3, 4, &layout)).unwrap();
+        assert_faces(&cubemap, true);
+
+        // La misma escena en las dos cruces tiene que dar caras idénticas: en la vertical la
+        // cara -Z está guardada cabeza abajo debajo de -Y
+        let horizontal = cross(4, 3, &[(2, 1), (0, 1), (1, 0), (1, 2), (1, 1), (3, 1)]);
+        let mut vertical = RgbaImage::new(3 * SIZE, 4 * SIZE);
+        for (column, row) in [(0, 1), (1, 0), (1, 1), (1, 2), (2, 1)] {
+            let tile = imageops::crop_imm(&horizontal, column * SIZE, row * SIZE, SIZE, SIZE);
+            imageops::replace(&mut vertical, &*tile, (column * SIZE) as i64, (row * SIZE) as i64);
+        }
+        let negative_z = imageops::crop_imm(&horizontal, 3 * SIZE, SIZE, SIZE, SIZE);
+        let flipped = imageops::rotate180(&*negative_z);
+        imageops::replace(&mut vertical, &flipped, SIZE as i64, (3 * SIZE) as i64);
+
+        let from_horizontal = Cubemap::from_cross_image(&horizontal).unwrap();
+        let from_vertical = Cubemap::from_cross_image(&vertical).unwrap();
+        assert_eq!(from_horizontal.faces, from_vertical.faces);
+    }
+
+    #[test]
+    fn other_aspect_ratios_are_rejected() {
+        assert!(Cubemap::from_cross_image(&RgbaImage::new(4, 4)).is_err());
+    }
+
+    #[test]
+    fn empty_image_is_rejected() {
+        assert!(Cubemap::from_cross_image(&RgbaImage::new(0, 0)).is_err());
+    }
+}
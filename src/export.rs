@@ -0,0 +1,96 @@
+use crate::scene::{scene_positions, OrbitState};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+// Muestrea las posiciones de la escena entre `start` y `end` y las escribe en CSV o JSON
+// según la extensión del archivo. Devuelve el número de filas escritas.
+pub fn export_orbit_timeline(
+    path: &str,
+    orbits: &OrbitState,
+    start: u32,
+    end: u32,
+    step: u32,
+) -> io::Result<usize> {
+    let is_json = Path::new(path)
+        .extension()
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    let mut writer = BufWriter::new(File::create(path)?);
+    let mut rows = 0;
+
+    if is_json {
+        writeln!(writer, "[")?;
+    } else {
+        writeln!(writer, "time,body,x,y,z")?;
+    }
+
+    for time in (start..=end).step_by(step as usize) {
+        for body in scene_positions(orbits, time) {
+            let p = body.position;
+            if is_json {
+                if rows > 0 {
+                    writeln!(writer, ",")?;
+                }
+                write!(
+                    writer,
+                    "  {{\"time\": {}, \"body\": {}, \"x\": {}, \"y\": {}, \"z\": {}}}",
+                    time,
+                    json_string(body.name),
+                    p.x,
+                    p.y,
+                    p.z
+                )?;
+            } else {
+                writeln!(writer, "{},{},{},{},{}", time, csv_field(body.name), p.x, p.y, p.z)?;
+            }
+            rows += 1;
+        }
+    }
+
+    if is_json {
+        writeln!(writer, "\n]")?;
+    }
+
+    writer.flush()?;
+    Ok(rows)
+}
+
+// Los nombres vienen de system.toml y pueden traer comillas, comas o saltos de línea, así que
+// se escriben como cadena JSON (entre comillas y con los caracteres especiales escapados)...
+fn json_string(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len() + 2);
+    escaped.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped.push('"');
+    escaped
+}
+
+// ...o como campo CSV entre comillas (RFC 4180), con las comillas internas duplicadas
+fn csv_field(text: &str) -> String {
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_are_escaped() {
+        assert_eq!(json_string("Sol"), "\"Sol\"");
+        assert_eq!(json_string("a \"b\"\\c\n"), "\"a \\\"b\\\"\\\\c\\n\"");
+        assert_eq!(csv_field("Sol"), "\"Sol\"");
+        assert_eq!(csv_field("a, \"b\""), "\"a, \"\"b\"\"\"");
+    }
+}
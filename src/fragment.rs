@@ -4,22 +4,38 @@ use crate::color::Color;
 
 pub struct Fragment {
     pub position: Vec2,
+    #[allow(dead_code)]
     pub color: Color,
     pub depth: f32,
     pub normal: Vec3,
     pub intensity: f32,
     pub vertex_position: Vec3,
+    pub tex_coords: (f32, f32),
+    // Fracción del píxel que cubre el triángulo: 1 salvo en los bordes con antialiasing
+    pub coverage: f32,
 }
 
 impl Fragment {
-    pub fn new(x: f32, y: f32, color: Color, depth: f32, normal: Vec3, intensity: f32, vertex_position: Vec3,) -> Self {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        x: f32,
+        y: f32,
+        color: Color,
+        depth: f32,
+        normal: Vec3,
+        intensity: f32,
+        vertex_position: Vec3,
+        tex_coords: (f32, f32),
+    ) -> Self {
         Fragment {
             position: Vec2::new(x, y),
             color,
             depth,
             normal,
             intensity,
-            vertex_position
+            vertex_position,
+            tex_coords,
+            coverage: 1.0,
         }
     }
 }
\ No newline at end of file
@@ -1,11 +1,76 @@
+use crate::color::Color;
+use crate::font::{glyph, GLYPH_WIDTH};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+#[derive(Clone, Copy, Debug)]
+pub struct Rect {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+// Cómo escribe point() el color actual: reemplazándolo (y ocupando el z-buffer) o
+// mezclándolo con lo que ya hay según la opacidad actual, sin tocar el z-buffer para que
+// varias capas transparentes se acumulen. Additive suma el color escalado por la opacidad,
+// para brillos que se refuerzan al superponerse
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BlendMode {
+    Opaque,
+    AlphaBlend,
+    Additive,
+}
+
+// Cómo dibuja render() los triángulos: rellenos con su shader o solo sus aristas, para
+// ver la teselación de los modelos
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum RenderMode {
+    Filled,
+    Wireframe,
+}
+
+// Normal de los fragmentos: interpolada entre los vértices (superficie suave) o la misma en
+// todo el triángulo, que deja ver las caras del modelo
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ShadingMode {
+    Smooth,
+    Flat,
+}
+
+impl Rect {
+    pub fn contains(&self, x: usize, y: usize) -> bool {
+        x >= self.x && y >= self.y && x < self.x + self.width && y < self.y + self.height
+    }
+
+    // El mismo rectángulo en un framebuffer `factor` veces mayor
+    pub fn scaled(&self, factor: usize) -> Rect {
+        Rect {
+            x: self.x * factor,
+            y: self.y * factor,
+            width: self.width * factor,
+            height: self.height * factor,
+        }
+    }
+}
 
 pub struct Framebuffer {
     pub width: usize,
     pub height: usize,
     pub buffer: Vec<u32>,
     pub zbuffer: Vec<f32>,
+    // Región de recorte: si existe, la escena 3D solo escribe dentro de ella
+    pub scissor: Option<Rect>,
+    // Contador de fragmentos por píxel del modo de depuración de sobredibujado
+    pub overdraw: Option<Vec<u32>>,
+    pub render_mode: RenderMode,
+    pub shading_mode: ShadingMode,
+    // Cobertura parcial en los bordes de los triángulos opacos (antialiasing solo de bordes)
+    pub edge_antialiasing: bool,
     background_color: u32,
     current_color: u32,
+    blend_mode: BlendMode,
+    current_alpha: f32,
 }
 
 impl Framebuffer {
@@ -15,31 +80,359 @@ impl Framebuffer {
             height,
             buffer: vec![0; width * height],
             zbuffer: vec![f32::INFINITY; width * height],
+            scissor: None,
+            overdraw: None,
+            render_mode: RenderMode::Filled,
+            shading_mode: ShadingMode::Smooth,
+            edge_antialiasing: false,
             background_color: 0x000000,
-            current_color: 0xFFFFFF
+            current_color: 0xFFFFFF,
+            blend_mode: BlendMode::Opaque,
+            current_alpha: 1.0,
         }
     }
 
     pub fn clear(&mut self) {
-        for pixel in self.buffer.iter_mut() {
-            *pixel = self.background_color;
+        if self.scissor.is_some() {
+            for index in 0..self.buffer.len() {
+                if self.in_scissor(index % self.width, index / self.width) {
+                    self.buffer[index] = self.background_color;
+                }
+            }
+        } else {
+            for pixel in self.buffer.iter_mut() {
+                *pixel = self.background_color;
+            }
+        }
+        if let Some(overdraw) = &mut self.overdraw {
+            overdraw.clear();
+            overdraw.resize(self.width * self.height, 0);
+        }
+        self.clear_depth();
+    }
+
+    // Oscurece el color en lugar de borrarlo, para dejar estelas de un cuadro a otro. El
+    // z-buffer no se toca: hay que reiniciarlo aparte con clear_depth. Se trunca en vez de
+    // redondear para que los restos lleguen a negro y no se queden en unos pocos niveles
+    pub fn fade(&mut self, factor: f32) {
+        let factor = factor.clamp(0.0, 1.0);
+        for index in 0..self.buffer.len() {
+            if !self.in_scissor(index % self.width, index / self.width) {
+                continue;
+            }
+            let pixel = self.buffer[index];
+            let channel = |shift: u32| (((pixel >> shift) & 0xFF) as f32 * factor) as u32;
+            self.buffer[index] = (channel(16) << 16) | (channel(8) << 8) | channel(0);
+        }
+    }
+
+    pub fn clear_depth(&mut self) {
+        if self.scissor.is_some() {
+            for index in 0..self.zbuffer.len() {
+                if self.in_scissor(index % self.width, index / self.width) {
+                    self.zbuffer[index] = f32::INFINITY;
+                }
+            }
+        } else {
+            self.zbuffer.fill(f32::INFINITY);
         }
-        for depth in self.zbuffer.iter_mut() {
-            *depth = f32::INFINITY;
+    }
+
+    pub fn in_scissor(&self, x: usize, y: usize) -> bool {
+        self.scissor.is_none_or(|rect| rect.contains(x, y))
+    }
+
+    // Reasigna ambos buffers para que siempre tengan width * height elementos
+    pub fn resize(&mut self, width: usize, height: usize) {
+        self.width = width;
+        self.height = height;
+        self.buffer = vec![self.background_color; width * height];
+        self.zbuffer = vec![f32::INFINITY; width * height];
+    }
+
+    // Reduce a este framebuffer otro `factor` veces mayor (supermuestreo): cada píxel es la
+    // media de su bloque de factor x factor muestras. La profundidad se queda con la más
+    // cercana del bloque y el sobredibujado con la media, para lo que se dibuje encima
+    pub fn downsample_from(&mut self, source: &Framebuffer, factor: usize) {
+        debug_assert!(source.width == self.width * factor && source.height == self.height * factor);
+        let samples = (factor * factor) as u32;
+        let average = |sum: u32| (sum + samples / 2) / samples;
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (mut r, mut g, mut b) = (0, 0, 0);
+                let mut depth = f32::INFINITY;
+                for sy in 0..factor {
+                    let row = (y * factor + sy) * source.width + x * factor;
+                    for i in row..row + factor {
+                        let pixel = source.buffer[i];
+                        r += (pixel >> 16) & 0xFF;
+                        g += (pixel >> 8) & 0xFF;
+                        b += pixel & 0xFF;
+                        depth = depth.min(source.zbuffer[i]);
+                    }
+                }
+                let index = y * self.width + x;
+                self.buffer[index] = (average(r) << 16) | (average(g) << 8) | average(b);
+                self.zbuffer[index] = depth;
+            }
         }
+
+        self.overdraw = source.overdraw.as_ref().map(|counts| {
+            (0..self.width * self.height)
+                .map(|index| {
+                    let (x, y) = (index % self.width, index / self.width);
+                    let block = (0..factor).flat_map(|sy| {
+                        let row = (y * factor + sy) * source.width + x * factor;
+                        row..row + factor
+                    });
+                    average(block.map(|i| counts[i]).sum())
+                })
+                .collect()
+        });
     }
 
     pub fn point(&mut self, x: usize, y: usize, depth: f32) {
-        if x < self.width && y < self.height {
+        if x < self.width && y < self.height && self.in_scissor(x, y) {
             let index = y * self.width + x;
 
             if self.zbuffer[index] > depth {
-                self.buffer[index] = self.current_color;
-                self.zbuffer[index] = depth;
+                match self.blend_mode {
+                    BlendMode::Opaque => {
+                        self.buffer[index] = self.current_color;
+                        self.zbuffer[index] = depth;
+                    }
+                    BlendMode::AlphaBlend => {
+                        self.buffer[index] =
+                            blend_colors(self.buffer[index], self.current_color, self.current_alpha);
+                    }
+                    BlendMode::Additive => {
+                        self.buffer[index] =
+                            add_colors(self.buffer[index], self.current_color, self.current_alpha);
+                    }
+                }
+            }
+        }
+    }
+
+    // Mezcla el color actual con la opacidad dada sin escribir en el z-buffer. Es parte de la
+    // escena, así que respeta la región de recorte (blend_pixel no, por el HUD)
+    pub fn blend_point(&mut self, x: usize, y: usize, alpha: f32) {
+        if self.in_scissor(x, y) {
+            self.blend_pixel(x, y, self.current_color, alpha);
+        }
+    }
+
+    // Mezcla un color sobre el píxel existente sin tocar el z-buffer (capas de HUD)
+    pub fn blend_pixel(&mut self, x: usize, y: usize, color: u32, alpha: f32) {
+        if x < self.width && y < self.height {
+            let blended = blend_colors(self.get_color(x, y), color, alpha);
+            self.buffer[y * self.width + x] = blended;
+        }
+    }
+
+    // Color ya dibujado en (x, y); fuera del framebuffer devuelve el color de fondo
+    pub fn get_color(&self, x: usize, y: usize) -> u32 {
+        if x < self.width && y < self.height {
+            self.buffer[y * self.width + x]
+        } else {
+            self.background_color
+        }
+    }
+
+    // Dibuja texto con la fuente de 5x7, recortando lo que salga del framebuffer
+    pub fn draw_text(&mut self, x: isize, y: isize, text: &str, color: u32) {
+        let mut cursor_x = x;
+        for c in text.chars() {
+            if cursor_x >= self.width as isize {
+                break;
+            }
+            for (row, bits) in glyph(c).iter().enumerate() {
+                for col in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - col)) == 0 {
+                        continue;
+                    }
+                    let px = cursor_x + col as isize;
+                    let py = y + row as isize;
+                    if px < 0 || py < 0 {
+                        continue;
+                    }
+                    let (px, py) = (px as usize, py as usize);
+                    if px < self.width && py < self.height {
+                        self.buffer[py * self.width + px] = color;
+                    }
+                }
+            }
+            cursor_x += (GLYPH_WIDTH + 1) as isize;
+        }
+    }
+
+    // Círculo sin antialiasing: relleno por tramos horizontales o contorno de un píxel de
+    // grosor con el algoritmo del punto medio. Lo que sale del framebuffer se recorta.
+    pub fn circle(&mut self, cx: isize, cy: isize, radius: isize, color: u32, filled: bool) {
+        if radius < 0 {
+            return;
+        }
+
+        let mut x = radius;
+        let mut y = 0;
+        let mut err = 1 - radius;
+
+        while x >= y {
+            if filled {
+                self.hspan(cx - x, cx + x, cy + y, color);
+                self.hspan(cx - x, cx + x, cy - y, color);
+                self.hspan(cx - y, cx + y, cy + x, color);
+                self.hspan(cx - y, cx + y, cy - x, color);
+            } else {
+                for &(px, py) in &[
+                    (x, y),
+                    (y, x),
+                    (-y, x),
+                    (-x, y),
+                    (-x, -y),
+                    (-y, -x),
+                    (y, -x),
+                    (x, -y),
+                ] {
+                    self.set_pixel(cx + px, cy + py, color);
+                }
+            }
+
+            y += 1;
+            if err < 0 {
+                err += 2 * y + 1;
+            } else {
+                x -= 1;
+                err += 2 * (y - x) + 1;
             }
         }
     }
 
+    // Círculo con antialiasing: la cobertura de cada píxel del borde se mezcla con lo que
+    // ya hay en el framebuffer
+    pub fn circle_aa(&mut self, cx: f32, cy: f32, radius: f32, color: u32, filled: bool) {
+        let frame = Rect {
+            x: 0,
+            y: 0,
+            width: self.width,
+            height: self.height,
+        };
+        self.circle_aa_clipped(cx, cy, radius, color, filled, &frame);
+    }
+
+    // Contorno con antialiasing que no escribe fuera de `clip` (paneles e insertos del HUD)
+    pub fn draw_circle(&mut self, cx: f32, cy: f32, radius: f32, color: u32, clip: &Rect) {
+        self.circle_aa_clipped(cx, cy, radius, color, false, clip);
+    }
+
+    // Disco con antialiasing que no escribe fuera de `clip`
+    pub fn draw_filled_circle(&mut self, cx: f32, cy: f32, radius: f32, color: u32, clip: &Rect) {
+        self.circle_aa_clipped(cx, cy, radius, color, true, clip);
+    }
+
+    fn circle_aa_clipped(
+        &mut self,
+        cx: f32,
+        cy: f32,
+        radius: f32,
+        color: u32,
+        filled: bool,
+        clip: &Rect,
+    ) {
+        if radius <= 0.0 {
+            return;
+        }
+
+        let min_x = ((cx - radius - 1.0).floor().max(0.0) as usize).max(clip.x);
+        let min_y = ((cy - radius - 1.0).floor().max(0.0) as usize).max(clip.y);
+        let max_x = ((cx + radius + 1.0).ceil().max(0.0) as usize)
+            .min(self.width)
+            .min(clip.x + clip.width);
+        let max_y = ((cy + radius + 1.0).ceil().max(0.0) as usize)
+            .min(self.height)
+            .min(clip.y + clip.height);
+
+        for y in min_y..max_y {
+            for x in min_x..max_x {
+                let dx = x as f32 + 0.5 - cx;
+                let dy = y as f32 + 0.5 - cy;
+                let distance = (dx * dx + dy * dy).sqrt();
+
+                let coverage = if filled {
+                    radius + 0.5 - distance
+                } else {
+                    1.0 - (distance - radius).abs()
+                };
+
+                if coverage > 0.0 {
+                    self.blend_pixel(x, y, color, coverage.min(1.0));
+                }
+            }
+        }
+    }
+
+    fn set_pixel(&mut self, x: isize, y: isize, color: u32) {
+        if x >= 0 && y >= 0 && (x as usize) < self.width && (y as usize) < self.height {
+            self.buffer[y as usize * self.width + x as usize] = color;
+        }
+    }
+
+    fn hspan(&mut self, x0: isize, x1: isize, y: isize, color: u32) {
+        if y < 0 || y as usize >= self.height {
+            return;
+        }
+        let start = x0.max(0);
+        let end = x1.min(self.width as isize - 1);
+        for x in start..=end {
+            self.buffer[y as usize * self.width + x as usize] = color;
+        }
+    }
+
+    // Guarda el buffer de color como PPM binario (P6), sin dependencias de codificación
+    pub fn save_ppm(&self, path: &str) -> io::Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        write!(file, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for &pixel in &self.buffer {
+            let color = Color::from_hex(pixel);
+            file.write_all(&[color.r, color.g, color.b])?;
+        }
+        file.flush()
+    }
+
+    // Guarda el buffer de color como imagen; el formato (PNG, JPEG...) sale de la extensión
+    pub fn save_image(&self, path: &str) -> image::ImageResult<()> {
+        let image = image::RgbImage::from_fn(self.width as u32, self.height as u32, |x, y| {
+            let color = Color::from_hex(self.buffer[y as usize * self.width + x as usize]);
+            image::Rgb([color.r, color.g, color.b])
+        });
+        image.save(path)
+    }
+
+    // Sustituye el color por la profundidad en escala de grises: lo cercano oscuro y lo
+    // lejano claro. El z-buffer guarda z en NDC, que se pasa a distancia con near/far y se
+    // reparte en escala logarítmica para distinguir tanto lo próximo como lo lejano. Los
+    // píxeles sin escribir (INFINITY) quedan en blanco puro y el fondo (f32::MAX) en negro
+    // puro; la geometría usa sólo los grises intermedios.
+    pub fn render_depth_to_color(&mut self, near: f32, far: f32) {
+        let range = (far / near).ln();
+        for index in 0..self.buffer.len() {
+            if !self.in_scissor(index % self.width, index / self.width) {
+                continue;
+            }
+            let depth = self.zbuffer[index];
+            let gray = if depth == f32::INFINITY {
+                255
+            } else if depth == f32::MAX {
+                0
+            } else {
+                let distance = 2.0 * near * far / ((far + near) - depth * (far - near));
+                let t = ((distance / near).ln() / range).clamp(0.0, 1.0);
+                1 + (t * 253.0).round() as u32
+            };
+            self.buffer[index] = (gray << 16) | (gray << 8) | gray;
+        }
+    }
+
     pub fn set_background_color(&mut self, color: u32) {
         self.background_color = color;
     }
@@ -48,6 +441,19 @@ impl Framebuffer {
         self.current_color = color;
     }
 
+    pub fn set_blend_mode(&mut self, blend_mode: BlendMode) {
+        self.blend_mode = blend_mode;
+    }
+
+    pub fn blend_mode(&self) -> BlendMode {
+        self.blend_mode
+    }
+
+    // Opacidad con la que point() mezcla en BlendMode::AlphaBlend y BlendMode::Additive
+    pub fn set_current_alpha(&mut self, alpha: f32) {
+        self.current_alpha = alpha;
+    }
+
     pub fn line(&mut self, x0: usize, y0: usize, x1: usize, y1: usize) {
         let mut x0 = x0 as isize;
         let mut y0 = y0 as isize;
@@ -84,3 +490,120 @@ impl Framebuffer {
         }
     }
 }
+
+// Suma src escalado por alpha a dst, saturando cada canal en 255
+pub fn add_colors(dst: u32, src: u32, alpha: f32) -> u32 {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let channel = |shift: u32| {
+        let d = ((dst >> shift) & 0xFF) as f32;
+        let s = ((src >> shift) & 0xFF) as f32;
+        ((d + s * alpha).round().min(255.0) as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+pub fn blend_colors(dst: u32, src: u32, alpha: f32) -> u32 {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let channel = |shift: u32| {
+        let d = ((dst >> shift) & 0xFF) as f32;
+        let s = ((src >> shift) & 0xFF) as f32;
+        ((s * alpha + d * (1.0 - alpha)).round() as u32) << shift
+    };
+    channel(16) | channel(8) | channel(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resize_and_clear_depth_keep_both_buffers_consistent() {
+        let mut framebuffer = Framebuffer::new(4, 3);
+        framebuffer.set_background_color(0x102030);
+        framebuffer.point(1, 1, 0.5);
+
+        framebuffer.resize(5, 2);
+        assert_eq!(framebuffer.buffer.len(), 10);
+        assert_eq!(framebuffer.zbuffer.len(), 10);
+        assert!(framebuffer.buffer.iter().all(|&pixel| pixel == 0x102030));
+        assert!(framebuffer.zbuffer.iter().all(|&depth| depth == f32::INFINITY));
+
+        framebuffer.point(4, 1, 0.25);
+        framebuffer.point(0, 0, 0.75);
+        framebuffer.clear_depth();
+        assert!(framebuffer.zbuffer.iter().all(|&depth| depth == f32::INFINITY));
+        assert_eq!(framebuffer.get_color(4, 1), 0xFFFFFF);
+    }
+
+    #[test]
+    fn save_ppm_writes_a_p6_header_and_rgb_pixels() {
+        let mut framebuffer = Framebuffer::new(3, 2);
+        framebuffer.buffer[0] = 0x123456;
+        framebuffer.buffer[5] = 0xFF8000;
+
+        let path = std::env::temp_dir().join(format!("save_ppm_{}.ppm", std::process::id()));
+        framebuffer.save_ppm(path.to_str().unwrap()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let header = b"P6\n3 2\n255\n";
+        assert_eq!(&bytes[..header.len()], header);
+        let pixels = &bytes[header.len()..];
+        assert_eq!(pixels.len(), 3 * 2 * 3);
+        assert_eq!(&pixels[0..3], &[0x12, 0x34, 0x56]);
+        assert_eq!(&pixels[3..6], &[0, 0, 0]);
+        assert_eq!(&pixels[15..18], &[0xFF, 0x80, 0x00]);
+    }
+
+    fn lit_pixels(framebuffer: &Framebuffer) -> Vec<(usize, usize)> {
+        (0..framebuffer.height)
+            .flat_map(|y| (0..framebuffer.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| framebuffer.get_color(x, y) != 0)
+            .collect()
+    }
+
+    // Suma de la cobertura de un dibujo blanco sobre negro, en píxeles
+    fn total_coverage(framebuffer: &Framebuffer) -> f32 {
+        framebuffer.buffer.iter().map(|&pixel| (pixel & 0xFF) as f32 / 255.0).sum()
+    }
+
+    #[test]
+    fn filled_circle_covers_about_pi_r_squared() {
+        let radius = 20.0;
+        let area = std::f32::consts::PI * radius * radius;
+
+        let mut framebuffer = Framebuffer::new(64, 64);
+        framebuffer.circle(32, 32, radius as isize, 0xFFFFFF, true);
+        let count = lit_pixels(&framebuffer).len() as f32;
+        assert!((count - area).abs() / area < 0.05, "{} píxeles para {}", count, area);
+
+        // Con antialiasing lo que se acerca al área es la suma de las coberturas
+        let mut framebuffer = Framebuffer::new(64, 64);
+        framebuffer.circle_aa(32.0, 32.0, radius, 0xFFFFFF, true);
+        let coverage = total_coverage(&framebuffer);
+        assert!((coverage - area).abs() / area < 0.02, "cobertura {} para {}", coverage, area);
+    }
+
+    #[test]
+    fn circle_outline_is_one_pixel_thick() {
+        let mut framebuffer = Framebuffer::new(64, 64);
+        framebuffer.circle(32, 32, 20, 0xFFFFFF, false);
+        let pixels = lit_pixels(&framebuffer);
+        assert!(!pixels.is_empty());
+        for &(x, y) in &pixels {
+            let distance = ((x as f32 - 32.0).powi(2) + (y as f32 - 32.0).powi(2)).sqrt();
+            assert!((distance - 20.0).abs() < 1.0, "({}, {}) a {}", x, y, distance);
+            // Un trazo de un píxel nunca llena un bloque de 2x2
+            let block = [(x + 1, y), (x, y + 1), (x + 1, y + 1)];
+            assert!(block.iter().any(|&(bx, by)| framebuffer.get_color(bx, by) == 0));
+        }
+
+        // El contorno con antialiasing reparte un píxel de cobertura por cada píxel de
+        // perímetro
+        let mut framebuffer = Framebuffer::new(64, 64);
+        framebuffer.circle_aa(32.0, 32.0, 20.0, 0xFFFFFF, false);
+        let coverage = total_coverage(&framebuffer);
+        let perimeter = 2.0 * std::f32::consts::PI * 20.0;
+        assert!((coverage - perimeter).abs() / perimeter < 0.05, "cobertura {}", coverage);
+    }
+}
@@ -0,0 +1,136 @@
+use gilrs::{Axis, Button, EventType, GamepadId, Gilrs};
+
+// Fracción del recorrido de cada stick o gatillo que se ignora, para que un mando en reposo
+// no haga derivar la cámara
+const STICK_DEADZONE: f32 = 0.2;
+const TRIGGER_DEADZONE: f32 = 0.1;
+
+// Estado de un mando en un cuadro, con las zonas muertas ya aplicadas. Los sticks van de -1
+// a 1 con el eje Y positivo hacia arriba; `zoom` es positivo al acercarse (gatillo derecho) y
+// `vertical` positivo al subir (botón inferior) o negativo al bajar (botón derecho)
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct GamepadState {
+    pub movement: (f32, f32),
+    pub look: (f32, f32),
+    pub zoom: f32,
+    pub vertical: f32,
+}
+
+impl GamepadState {
+    pub fn is_active(&self) -> bool {
+        *self != GamepadState::default()
+    }
+}
+
+// Mando activo, si hay alguno. Se admite conectar y desconectar en caliente: al perder el
+// mando activo se pasa a otro conectado o, si no queda ninguno, se sigue solo con teclado.
+pub struct Gamepad {
+    gilrs: Option<Gilrs>,
+    active: Option<GamepadId>,
+}
+
+impl Gamepad {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(err) => {
+                eprintln!("Mandos no disponibles, solo teclado: {}", err);
+                None
+            }
+        };
+        let active = gilrs
+            .as_ref()
+            .and_then(|gilrs| gilrs.gamepads().next().map(|(id, _)| id));
+        if let (Some(gilrs), Some(id)) = (&gilrs, active) {
+            println!("Mando conectado: {}", gilrs.gamepad(id).name());
+        }
+        Gamepad { gilrs, active }
+    }
+
+    // Procesa las conexiones y desconexiones pendientes y lee el mando activo
+    pub fn poll(&mut self) -> GamepadState {
+        let Some(gilrs) = &mut self.gilrs else {
+            return GamepadState::default();
+        };
+        while let Some(event) = gilrs.next_event() {
+            match event.event {
+                EventType::Connected if self.active.is_none() => {
+                    self.active = Some(event.id);
+                    println!("Mando conectado: {}", gilrs.gamepad(event.id).name());
+                }
+                EventType::Disconnected if self.active == Some(event.id) => {
+                    self.active = gilrs
+                        .gamepads()
+                        .map(|(id, _)| id)
+                        .find(|&id| id != event.id);
+                    match self.active {
+                        Some(id) => println!("Mando conectado: {}", gilrs.gamepad(id).name()),
+                        None => println!("Mando desconectado, solo teclado"),
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        let Some(pad) = self.active.and_then(|id| gilrs.connected_gamepad(id)) else {
+            return GamepadState::default();
+        };
+        let trigger = |button| {
+            let value = pad.button_data(button).map_or(0.0, |data| data.value());
+            deadzone(value, TRIGGER_DEADZONE)
+        };
+        let pressed = |button| if pad.is_pressed(button) { 1.0 } else { 0.0 };
+        GamepadState {
+            movement: stick_deadzone(pad.value(Axis::LeftStickX), pad.value(Axis::LeftStickY)),
+            look: stick_deadzone(pad.value(Axis::RightStickX), pad.value(Axis::RightStickY)),
+            zoom: trigger(Button::RightTrigger2) - trigger(Button::LeftTrigger2),
+            vertical: pressed(Button::South) - pressed(Button::East),
+        }
+    }
+}
+
+// Anula los valores dentro de la zona muerta y reescala el resto para que la respuesta siga
+// empezando en 0 al salir de ella y llegue a 1 al final del recorrido
+fn deadzone(value: f32, threshold: f32) -> f32 {
+    if value.abs() <= threshold {
+        0.0
+    } else {
+        value.signum() * ((value.abs() - threshold) / (1.0 - threshold)).min(1.0)
+    }
+}
+
+// Zona muerta radial: se mira la inclinación total del stick, no cada eje por separado, así
+// que las diagonales no se recortan hacia los ejes
+fn stick_deadzone(x: f32, y: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= STICK_DEADZONE {
+        return (0.0, 0.0);
+    }
+    let scale = deadzone(magnitude, STICK_DEADZONE) / magnitude;
+    (x * scale, y * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn resting_stick_does_not_drift() {
+        assert_eq!(stick_deadzone(0.05, -0.1), (0.0, 0.0));
+        assert_eq!(deadzone(-TRIGGER_DEADZONE, TRIGGER_DEADZONE), 0.0);
+    }
+
+    #[test]
+    fn deadzone_rescales_from_edge_to_full_tilt() {
+        assert!(deadzone(STICK_DEADZONE + 1e-4, STICK_DEADZONE) < 1e-3);
+        assert_eq!(deadzone(1.0, STICK_DEADZONE), 1.0);
+        assert_eq!(deadzone(-1.0, STICK_DEADZONE), -1.0);
+    }
+
+    #[test]
+    fn stick_deadzone_keeps_direction() {
+        let (x, y) = stick_deadzone(0.6, 0.8);
+        assert!((x / y - 0.75).abs() < 1e-6);
+        assert!(((x * x + y * y).sqrt() - 1.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,375 @@
+use crate::camera::Camera;
+use crate::font::{text_width, GLYPH_HEIGHT};
+use crate::framebuffer::{Framebuffer, Rect};
+use crate::scene::{self, Body, OrbitState, Planet};
+use crate::shaders::ShaderType;
+use crate::uniforms::{project_to_screen, Uniforms};
+use nalgebra_glm::{Vec2, Vec3};
+use std::f32::consts::PI;
+
+const HUD_COLOR: u32 = 0x40FF80;
+const COCKPIT_COLOR: u32 = 0x1A1D24;
+const COCKPIT_OPACITY: f32 = 0.95;
+const PANEL_COLOR: u32 = 0x0A0E18;
+const PANEL_OPACITY: f32 = 0.75;
+const PANEL_PADDING: usize = 8;
+const PANEL_LINE_HEIGHT: usize = GLYPH_HEIGHT + 5;
+const FRAME_STATS_SMOOTHING: f32 = 0.1;
+const MINIMAP_SIZE: usize = 150;
+const MINIMAP_MARGIN: usize = 10;
+const MINIMAP_ORBIT_COLOR: u32 = 0x2A4A3A;
+const MINIMAP_SUN_COLOR: u32 = 0xFFC040;
+const MINIMAP_SHIP_COLOR: u32 = 0xFFFFFF;
+
+// Marco de cabina dibujado proceduralmente sobre la escena: arco superior, dos
+// montantes diagonales y el tablero inferior. El centro queda transparente.
+pub fn render_cockpit(framebuffer: &mut Framebuffer) {
+    let width = framebuffer.width as f32;
+    let height = framebuffer.height as f32;
+
+    let strut_half_width = width * 0.012;
+    let left_strut = (
+        Vec2::new(width * 0.30, 0.0),
+        Vec2::new(width * 0.02, height * 0.85),
+    );
+    let right_strut = (
+        Vec2::new(width * 0.70, 0.0),
+        Vec2::new(width * 0.98, height * 0.85),
+    );
+
+    for y in 0..framebuffer.height {
+        for x in 0..framebuffer.width {
+            let p = Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let u = p.x / width * 2.0 - 1.0;
+
+            // Distancias con signo en píxeles (positivas dentro de la forma)
+            let dashboard = p.y - height * (0.80 + 0.06 * u * u);
+            let canopy = height * 0.05 - p.y;
+            let left = strut_half_width - distance_to_segment(&p, &left_strut.0, &left_strut.1);
+            let right = strut_half_width - distance_to_segment(&p, &right_strut.0, &right_strut.1);
+
+            let inside = dashboard.max(canopy).max(left).max(right);
+            let coverage = (inside + 0.5).clamp(0.0, 1.0);
+
+            if coverage > 0.0 {
+                framebuffer.blend_pixel(x, y, COCKPIT_COLOR, coverage * COCKPIT_OPACITY);
+            }
+        }
+    }
+}
+
+fn distance_to_segment(p: &Vec2, a: &Vec2, b: &Vec2) -> f32 {
+    let ab = b - a;
+    let t = ((p - a).dot(&ab) / ab.dot(&ab)).clamp(0.0, 1.0);
+    (p - (a + ab * t)).magnitude()
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LabelMode {
+    Hidden,
+    Names,
+    Degrees,
+    Orbits,
+}
+
+impl LabelMode {
+    pub fn next(self) -> Self {
+        match self {
+            LabelMode::Hidden => LabelMode::Names,
+            LabelMode::Names => LabelMode::Degrees,
+            LabelMode::Degrees => LabelMode::Orbits,
+            LabelMode::Orbits => LabelMode::Hidden,
+        }
+    }
+}
+
+// Texto de la etiqueta de un planeta según el modo: nombre, ángulo orbital actual
+// o número de órbitas completadas desde el inicio de la simulación.
+pub fn planet_label(name: &str, mode: LabelMode, orbital_angle: f32) -> Option<String> {
+    match mode {
+        LabelMode::Hidden => None,
+        LabelMode::Names => Some(name.to_string()),
+        LabelMode::Degrees => Some(format!(
+            "{} {:.0}°",
+            name,
+            orbital_angle.to_degrees().rem_euclid(360.0)
+        )),
+        LabelMode::Orbits => Some(format!("{} {:.2} orbitas", name, orbital_angle / (2.0 * PI))),
+    }
+}
+
+// Etiqueta centrada horizontalmente y apoyada sobre (x, y), con sombra para leerse sobre el cielo
+pub fn draw_label(framebuffer: &mut Framebuffer, x: f32, y: f32, text: &str, color: u32) {
+    let left = x as isize - text_width(text) as isize / 2;
+    let top = y as isize - GLYPH_HEIGHT as isize;
+    framebuffer.draw_text(left + 1, top + 1, text, 0x000000);
+    framebuffer.draw_text(left, top, text, color);
+}
+
+// Brillo actual del fondo en la esquina superior derecha
+pub fn render_skybox_exposure(framebuffer: &mut Framebuffer, exposure: f32) {
+    let text = format!("FONDO: X{:.1}", exposure);
+    let x = framebuffer.width as isize - text_width(&text) as isize - 10;
+    framebuffer.draw_text(x + 1, 11, &text, 0x000000);
+    framebuffer.draw_text(x, 10, &text, HUD_COLOR);
+}
+
+// Luz ambiente actual, debajo del brillo del fondo
+pub fn render_ambient_light(framebuffer: &mut Framebuffer, ambient: f32) {
+    let text = format!("AMBIENTE: {:.2}", ambient);
+    let x = framebuffer.width as isize - text_width(&text) as isize - 10;
+    framebuffer.draw_text(x + 1, 23, &text, 0x000000);
+    framebuffer.draw_text(x, 22, &text, HUD_COLOR);
+}
+
+// Tiempos suavizados con una media exponencial para que la lectura no salte cada cuadro
+pub struct FrameStats {
+    frame_time: f32,
+    render_time: f32,
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        FrameStats {
+            frame_time: 0.0,
+            render_time: 0.0,
+        }
+    }
+
+    pub fn update(&mut self, frame_time: f32, render_time: f32) {
+        if self.frame_time == 0.0 {
+            self.frame_time = frame_time;
+            self.render_time = render_time;
+        } else {
+            self.frame_time += (frame_time - self.frame_time) * FRAME_STATS_SMOOTHING;
+            self.render_time += (render_time - self.render_time) * FRAME_STATS_SMOOTHING;
+        }
+    }
+}
+
+// Cuadros por segundo y tiempo de renderizado en la esquina superior izquierda
+pub fn render_frame_stats(framebuffer: &mut Framebuffer, stats: &FrameStats) {
+    let fps = if stats.frame_time > 0.0 { 1.0 / stats.frame_time } else { 0.0 };
+    let lines = [
+        format!("FPS: {:.0}", fps),
+        format!("RENDER: {:.1} MS", stats.render_time * 1000.0),
+    ];
+    for (row, text) in lines.iter().enumerate() {
+        let y = 10 + (row * PANEL_LINE_HEIGHT) as isize;
+        framebuffer.draw_text(11, y + 1, text, 0x000000);
+        framebuffer.draw_text(10, y, text, HUD_COLOR);
+    }
+}
+
+// Velocidad numérica y marcador de trayectoria: un círculo con "alas" en el punto de la
+// pantalla hacia el que se está desplazando la cámara.
+pub fn render_velocity_hud(
+    framebuffer: &mut Framebuffer,
+    eye: &Vec3,
+    velocity: &Vec3,
+    frame_time: f32,
+    uniforms: &Uniforms,
+) {
+    let distance_per_frame = velocity.magnitude();
+    let speed = if frame_time > 0.0 {
+        distance_per_frame / frame_time
+    } else {
+        0.0
+    };
+    let text = format!("VEL: {:.1} U/S", speed);
+    let y = framebuffer.height as isize - GLYPH_HEIGHT as isize - 10;
+    framebuffer.draw_text(11, y + 1, &text, 0x000000);
+    framebuffer.draw_text(10, y, &text, HUD_COLOR);
+
+    if distance_per_frame <= f32::EPSILON {
+        return;
+    }
+
+    let target = eye + velocity.normalize() * 100.0;
+    let screen = match project_to_screen(&target, uniforms) {
+        Some(screen) => screen,
+        None => return,
+    };
+
+    let radius = 6.0;
+    let wing = 8.0;
+    let margin = radius + wing + 1.0;
+    if screen.x < margin
+        || screen.y < margin
+        || screen.x >= framebuffer.width as f32 - margin
+        || screen.y >= framebuffer.height as f32 - margin
+    {
+        return;
+    }
+
+    let (cx, cy) = (screen.x, screen.y);
+    framebuffer.circle_aa(cx, cy, radius, HUD_COLOR, false);
+    framebuffer.circle(cx as isize, cy as isize, 1, HUD_COLOR, true);
+
+    framebuffer.set_current_color(HUD_COLOR);
+    framebuffer.line(
+        (cx - radius - wing) as usize,
+        cy as usize,
+        (cx - radius) as usize,
+        cy as usize,
+    );
+    framebuffer.line(
+        (cx + radius) as usize,
+        cy as usize,
+        (cx + radius + wing) as usize,
+        cy as usize,
+    );
+    framebuffer.line(
+        cx as usize,
+        (cy - radius - wing * 0.6) as usize,
+        cx as usize,
+        (cy - radius) as usize,
+    );
+}
+
+fn format_period(period: f32) -> String {
+    if period.is_finite() {
+        format!("{:.0} cuadros", period)
+    } else {
+        "sin rotacion".to_string()
+    }
+}
+
+// Panel lateral con los datos del cuerpo seleccionado, recalculados en cada cuadro
+pub fn render_body_inspector(
+    framebuffer: &mut Framebuffer,
+    body: Body,
+    orbits: &OrbitState,
+    time: u32,
+    shader: &ShaderType,
+    camera_eye: &Vec3,
+) {
+    let angle = body.orbital_angle(orbits, time).to_degrees().rem_euclid(360.0);
+    let distance = (camera_eye - body.position(orbits, time)).magnitude();
+
+    let planets = orbits.planets();
+    let mut lines = vec![body.name(planets).to_string()];
+    match body {
+        Body::Sun => {}
+        Body::Planet(index) => {
+            lines.extend([
+                format!("Radio orbital: {:.1}", planets[index].radius),
+                format!("Posicion: {:.0}°", angle),
+                format!("Rotacion: {}", format_period(orbits.rotation_period(index))),
+            ]);
+        }
+        Body::Moon => {
+            let period = if scene::MOON_TIDALLY_LOCKED {
+                2.0 * PI / scene::MOON_ORBIT_SPEED
+            } else {
+                2.0 * PI / scene::MOON_ROTATION_SPEED
+            };
+            lines.extend([
+                format!("Orbita: {}", planets[scene::moon_parent(planets)].name),
+                format!("Radio orbital: {:.1}", scene::MOON_ORBIT_RADIUS),
+                format!("Posicion: {:.0}°", angle),
+                format!("Rotacion: {}", format_period(period)),
+            ]);
+        }
+    }
+    lines.extend([
+        format!("Escala: {:.1}", body.scale(planets)),
+        format!("Shader: {:?}", shader),
+        format!("Distancia: {:.1}", distance),
+    ]);
+    if let Body::Planet(index) = body {
+        let has_moon = if planets[index].has_moon { "si" } else { "no" };
+        lines.push(format!("Luna: {}", has_moon));
+    }
+
+    let content_width = lines.iter().map(|line| text_width(line)).max().unwrap_or(0);
+    let panel_width = content_width + PANEL_PADDING * 2;
+    let panel_height = lines.len() * PANEL_LINE_HEIGHT + PANEL_PADDING * 2 - 5;
+    let left = framebuffer.width.saturating_sub(panel_width + 10);
+    let top = 10;
+
+    for y in top..(top + panel_height).min(framebuffer.height) {
+        for x in left..(left + panel_width).min(framebuffer.width) {
+            framebuffer.blend_pixel(x, y, PANEL_COLOR, PANEL_OPACITY);
+        }
+    }
+
+    for (row, line) in lines.iter().enumerate() {
+        let color = if row == 0 { 0xFFFFFF } else { HUD_COLOR };
+        framebuffer.draw_text(
+            (left + PANEL_PADDING) as isize,
+            (top + PANEL_PADDING + row * PANEL_LINE_HEIGHT) as isize,
+            line,
+            color,
+        );
+    }
+}
+
+// Vista cenital esquemática en la esquina inferior derecha: el sol en el centro, las órbitas,
+// la posición actual de cada planeta y la nave (la cámara). El plano XZ del mundo se escala
+// para que quepa la órbita más externa; la nave, si queda fuera, se fija al borde del inserto
+// para seguir indicando hacia dónde está. Nada se dibuja fuera del inserto.
+pub fn render_minimap(
+    framebuffer: &mut Framebuffer,
+    planets: &[Planet],
+    planet_positions: &[Vec3],
+    camera: &Camera,
+) {
+    if framebuffer.width < MINIMAP_SIZE + MINIMAP_MARGIN
+        || framebuffer.height < MINIMAP_SIZE + MINIMAP_MARGIN
+    {
+        return;
+    }
+    let inset = Rect {
+        x: framebuffer.width - MINIMAP_SIZE - MINIMAP_MARGIN,
+        y: framebuffer.height - MINIMAP_SIZE - MINIMAP_MARGIN,
+        width: MINIMAP_SIZE,
+        height: MINIMAP_SIZE,
+    };
+
+    for y in inset.y..inset.y + inset.height {
+        for x in inset.x..inset.x + inset.width {
+            framebuffer.blend_pixel(x, y, PANEL_COLOR, PANEL_OPACITY);
+        }
+    }
+
+    let half = MINIMAP_SIZE as f32 * 0.5;
+    let center = Vec2::new(inset.x as f32 + half, inset.y as f32 + half);
+    let outer_radius = planets.iter().map(|planet| planet.max_distance()).fold(0.0, f32::max);
+    let scale = (half - 6.0) / outer_radius;
+    let to_inset = |point: &Vec3| center + Vec2::new(point.x, point.z) * scale;
+
+    for planet in planets {
+        if planet.eccentricity == 0.0 && planet.inclination == 0.0 {
+            let radius = planet.radius * scale;
+            framebuffer.draw_circle(center.x, center.y, radius, MINIMAP_ORBIT_COLOR, &inset);
+            continue;
+        }
+        // Elipse vista desde arriba: se marca punto a punto, con unos dos puntos por píxel
+        // de perímetro para que el trazo quede continuo
+        let steps = (4.0 * PI * planet.max_distance() * scale).ceil() as usize;
+        for step in 0..steps {
+            let angle = 2.0 * PI * step as f32 / steps as f32;
+            let point = to_inset(&planet.orbit_point(angle));
+            let (x, y) = (point.x as usize, point.y as usize);
+            if point.x >= 0.0 && point.y >= 0.0 && inset.contains(x, y) {
+                framebuffer.blend_pixel(x, y, MINIMAP_ORBIT_COLOR, 1.0);
+            }
+        }
+    }
+    framebuffer.draw_filled_circle(center.x, center.y, 4.0, MINIMAP_SUN_COLOR, &inset);
+
+    for position in planet_positions {
+        let dot = to_inset(position);
+        framebuffer.draw_filled_circle(dot.x, dot.y, 2.5, HUD_COLOR, &inset);
+    }
+
+    let limit = half - 3.0;
+    let ship = to_inset(&camera.eye);
+    let ship = Vec2::new(
+        ship.x.clamp(center.x - limit, center.x + limit),
+        ship.y.clamp(center.y - limit, center.y + limit),
+    );
+    framebuffer.draw_circle(ship.x, ship.y, 3.0, MINIMAP_SHIP_COLOR, &inset);
+    framebuffer.draw_filled_circle(ship.x, ship.y, 1.0, MINIMAP_SHIP_COLOR, &inset);
+}
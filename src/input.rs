@@ -0,0 +1,330 @@
+use crate::gamepad::{Gamepad, GamepadState};
+use minifb::{Key, KeyRepeat, MouseButton, MouseMode, Window};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+
+// Teclas que consulta la simulación. Cada una ocupa un bit en los registros de la
+// grabación, así que una tecla nueva debe añadirse aquí (al final, para no invalidar
+// grabaciones anteriores).
+const TRACKED_KEYS: [Key; 57] = [
+    Key::Key1,
+    Key::Key2,
+    Key::Key3,
+    Key::Key4,
+    Key::Key5,
+    Key::Key6,
+    Key::LeftBracket,
+    Key::RightBracket,
+    Key::W,
+    Key::S,
+    Key::A,
+    Key::D,
+    Key::R,
+    Key::F,
+    Key::Left,
+    Key::Right,
+    Key::Up,
+    Key::Down,
+    Key::Q,
+    Key::E,
+    Key::K,
+    Key::X,
+    Key::H,
+    Key::N,
+    Key::L,
+    Key::T,
+    Key::B,
+    Key::V,
+    Key::I,
+    Key::M,
+    Key::P,
+    Key::O,
+    Key::Backslash,
+    Key::J,
+    Key::Comma,
+    Key::Period,
+    Key::Tab,
+    Key::U,
+    Key::Y,
+    Key::F1,
+    Key::C,
+    Key::Space,
+    Key::F2,
+    Key::F3,
+    Key::Z,
+    Key::F4,
+    Key::F5,
+    Key::Equal,
+    Key::Minus,
+    Key::NumPadPlus,
+    Key::NumPadMinus,
+    Key::G,
+    Key::F6,
+    Key::Home,
+    Key::LeftShift,
+    Key::PageUp,
+    Key::PageDown,
+];
+
+// Entrada de un cuadro: teclas mantenidas, recién pulsadas (con y sin repetición), si
+// hubo actividad del ratón, cuánto se arrastró con el botón derecho (en píxeles), el giro
+// vertical de la rueda, el estado del mando y dónde se hizo clic izquierdo, si se hizo
+#[derive(Clone, Copy, Default)]
+pub struct FrameInput {
+    down: u64,
+    pressed: u64,
+    repeated: u64,
+    mouse_activity: bool,
+    mouse_drag: (f32, f32),
+    scroll: f32,
+    gamepad: GamepadState,
+    click: Option<(f32, f32)>,
+}
+
+impl FrameInput {
+    fn capture(
+        window: &Window,
+        mouse_moved: bool,
+        mouse_drag: (f32, f32),
+        gamepad: GamepadState,
+        click: Option<(f32, f32)>,
+    ) -> Self {
+        let mut frame = FrameInput {
+            mouse_activity: mouse_moved
+                || window.get_mouse_down(MouseButton::Left)
+                || window.get_mouse_down(MouseButton::Right)
+                || window.get_scroll_wheel().is_some(),
+            mouse_drag,
+            scroll: window.get_scroll_wheel().map_or(0.0, |(_, y)| y),
+            gamepad,
+            click,
+            ..FrameInput::default()
+        };
+        for (bit, &key) in TRACKED_KEYS.iter().enumerate() {
+            if window.is_key_down(key) {
+                frame.down |= 1 << bit;
+            }
+            if window.is_key_pressed(key, KeyRepeat::No) {
+                frame.pressed |= 1 << bit;
+            }
+            if window.is_key_pressed(key, KeyRepeat::Yes) {
+                frame.repeated |= 1 << bit;
+            }
+        }
+        frame
+    }
+
+    // Una línea por cuadro: "down pressed repeated ratón dx dy rueda", con las máscaras en
+    // hexadecimal, seguida del mando: "mx my lx ly zoom vertical" y del clic: "clic x y"
+    fn to_line(self) -> String {
+        let (dx, dy) = self.mouse_drag;
+        let (clicked, (cx, cy)) = (self.click.is_some(), self.click.unwrap_or((0.0, 0.0)));
+        let GamepadState {
+            movement: (mx, my),
+            look: (lx, ly),
+            zoom,
+            vertical,
+        } = self.gamepad;
+        format!(
+            "{:x} {:x} {:x} {} {} {} {} {} {} {} {} {} {} {} {} {}",
+            self.down,
+            self.pressed,
+            self.repeated,
+            self.mouse_activity as u8,
+            dx,
+            dy,
+            self.scroll,
+            mx,
+            my,
+            lx,
+            ly,
+            zoom,
+            vertical,
+            clicked as u8,
+            cx,
+            cy
+        )
+    }
+
+    fn from_line(line: &str) -> Option<Self> {
+        let mut fields = line.split_whitespace();
+        let mut mask = || u64::from_str_radix(fields.next()?, 16).ok();
+        let (down, pressed, repeated) = (mask()?, mask()?, mask()?);
+        let mouse_activity = fields.next()? == "1";
+        // Las grabaciones anteriores no guardan el arrastre, la rueda, el mando ni el clic: se
+        // toman como nulos
+        let mut optional = || fields.next().map_or(Some(0.0), |field| field.parse().ok());
+        let mouse_drag = (optional()?, optional()?);
+        let scroll = optional()?;
+        let gamepad = GamepadState {
+            movement: (optional()?, optional()?),
+            look: (optional()?, optional()?),
+            zoom: optional()?,
+            vertical: optional()?,
+        };
+        let clicked = optional()? != 0.0;
+        let click = (optional()?, optional()?);
+        Some(FrameInput {
+            down,
+            pressed,
+            repeated,
+            mouse_activity,
+            mouse_drag,
+            scroll,
+            gamepad,
+            click: clicked.then_some(click),
+        })
+    }
+}
+
+fn key_bit(key: Key) -> u64 {
+    TRACKED_KEYS
+        .iter()
+        .position(|&tracked| tracked == key)
+        .map_or(0, |bit| 1 << bit)
+}
+
+// Fuente única de entrada para el bucle principal. En vivo lee la ventana; al reproducir
+// una sesión grabada entrega los cuadros del archivo en orden.
+pub struct Input {
+    frame: FrameInput,
+    last_mouse_pos: Option<(f32, f32)>,
+    // Posición del cursor en el cuadro anterior mientras se mantiene el botón derecho
+    drag_origin: Option<(f32, f32)>,
+    left_was_down: bool,
+    gamepad: Gamepad,
+    recorder: Option<BufWriter<File>>,
+    replay: Option<VecDeque<FrameInput>>,
+}
+
+impl Input {
+    pub fn new() -> Self {
+        Input {
+            frame: FrameInput::default(),
+            last_mouse_pos: None,
+            drag_origin: None,
+            left_was_down: false,
+            gamepad: Gamepad::new(),
+            recorder: None,
+            replay: None,
+        }
+    }
+
+    pub fn start_recording(&mut self, path: &str) -> io::Result<()> {
+        self.recorder = Some(BufWriter::new(File::create(path)?));
+        Ok(())
+    }
+
+    pub fn start_replay(&mut self, path: &str) -> io::Result<()> {
+        let mut frames = VecDeque::new();
+        for (number, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+            let line = line?;
+            let frame = FrameInput::from_line(&line).ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("Línea {} inválida: {}", number + 1, line),
+                )
+            })?;
+            frames.push_back(frame);
+        }
+        self.replay = Some(frames);
+        Ok(())
+    }
+
+    // Lee la entrada del cuadro actual y, si se está grabando, la añade al archivo
+    pub fn update(&mut self, window: &Window) -> io::Result<()> {
+        let mouse_pos = window.get_mouse_pos(MouseMode::Pass);
+        let mouse_moved = self.last_mouse_pos.is_some() && mouse_pos != self.last_mouse_pos;
+        self.last_mouse_pos = mouse_pos;
+
+        // El primer cuadro con el botón pulsado solo fija el origen, para que la vista no
+        // salte hacia donde estaba el cursor
+        let mut mouse_drag = (0.0, 0.0);
+        let drag_pos = mouse_pos.filter(|_| window.get_mouse_down(MouseButton::Right));
+        if let (Some((x, y)), Some((origin_x, origin_y))) = (drag_pos, self.drag_origin) {
+            mouse_drag = (x - origin_x, y - origin_y);
+        }
+        self.drag_origin = drag_pos;
+
+        // El clic se registra solo en el cuadro en que se pulsa el botón izquierdo
+        let left_down = window.get_mouse_down(MouseButton::Left);
+        let click = if left_down && !self.left_was_down {
+            window.get_mouse_pos(MouseMode::Discard)
+        } else {
+            None
+        };
+        self.left_was_down = left_down;
+
+        // El mando se consulta también durante la reproducción para no perder conexiones
+        let gamepad = self.gamepad.poll();
+
+        if let Some(frames) = &mut self.replay {
+            match frames.pop_front() {
+                Some(frame) => self.frame = frame,
+                None => {
+                    println!("Reproducción terminada, entrada en vivo");
+                    self.replay = None;
+                }
+            }
+        }
+        if self.replay.is_none() {
+            self.frame = FrameInput::capture(window, mouse_moved, mouse_drag, gamepad, click);
+        }
+
+        if let Some(recorder) = &mut self.recorder {
+            writeln!(recorder, "{}", self.frame.to_line())?;
+        }
+        Ok(())
+    }
+
+    pub fn stop_recording(&mut self) -> io::Result<()> {
+        if let Some(mut recorder) = self.recorder.take() {
+            recorder.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn is_key_down(&self, key: Key) -> bool {
+        self.frame.down & key_bit(key) != 0
+    }
+
+    pub fn is_key_pressed(&self, key: Key, repeat: KeyRepeat) -> bool {
+        let mask = match repeat {
+            KeyRepeat::No => self.frame.pressed,
+            KeyRepeat::Yes => self.frame.repeated,
+        };
+        mask & key_bit(key) != 0
+    }
+
+    // Desplazamiento del cursor desde el cuadro anterior mientras se arrastra con el botón
+    // derecho
+    pub fn mouse_drag(&self) -> (f32, f32) {
+        self.frame.mouse_drag
+    }
+
+    // Giro vertical de la rueda en este cuadro (positivo hacia arriba)
+    pub fn scroll(&self) -> f32 {
+        self.frame.scroll
+    }
+
+    // Posición en la ventana del clic izquierdo de este cuadro, si lo hubo
+    pub fn click(&self) -> Option<(f32, f32)> {
+        self.frame.click
+    }
+
+    // Sticks, gatillos y botones de vuelo del mando en este cuadro (nulos sin mando)
+    pub fn gamepad(&self) -> GamepadState {
+        self.frame.gamepad
+    }
+
+    // Si el botón derecho está mantenido en vivo (para ocultar el cursor durante el arrastre)
+    pub fn is_dragging(&self) -> bool {
+        self.drag_origin.is_some()
+    }
+
+    // Cualquier tecla, actividad del ratón o del mando en este cuadro (para el modo de reposo)
+    pub fn has_activity(&self) -> bool {
+        self.frame.down != 0 || self.frame.mouse_activity || self.frame.gamepad.is_active()
+    }
+}
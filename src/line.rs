@@ -3,6 +3,7 @@ use crate::vertex::Vertex;
 use crate::color::Color;
 use nalgebra_glm::Vec3;
 
+#[allow(dead_code)]
 pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let mut fragments = Vec::new();
 
@@ -27,7 +28,7 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let vertex_position = start; // Asignamos la posición inicial del vértice
 
     loop {
-        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x) as f32;
+        let z = start.z + (end.z - start.z) * (x0 - start.x as i32) as f32 / (end.x - start.x);
         fragments.push(Fragment::new(
             x0 as f32, 
             y0 as f32, 
@@ -36,6 +37,7 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
             normal, 
             intensity, 
             vertex_position,
+            (0.0, 0.0),
         ));
 
         if x0 == x1 && y0 == y1 { break; }
@@ -1,47 +1,258 @@
-use minifb::{Key, Window, WindowOptions};
-use nalgebra_glm::{look_at, perspective, Mat4, Vec3, Vec4};
-use rodio::{source::Source, Decoder, OutputStream, Sink};
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
+use nalgebra_glm::{perspective, Mat4, Vec2, Vec3, Vec4};
+use rodio::OutputStream;
 use std::f32::consts::PI;
-use std::fs::File;
-use std::io::BufReader;
-use std::time::Duration;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
+mod aabb;
+mod args;
+mod asteroids;
+mod audio;
 mod camera;
 mod color;
+mod comet;
+mod cubemap;
+mod export;
+mod font;
 mod fragment;
 mod framebuffer;
+mod gamepad;
+mod hud;
+mod input;
 mod line;
 mod obj;
+mod postprocess;
 mod ray_intersect;
+mod rings;
+mod scene;
 mod shaders;
+mod system;
 mod texture;
 mod triangle;
+mod uniforms;
 mod vertex;
 
 use crate::texture::Texture;
-use camera::Camera;
-use color::Color;
+use aabb::Aabb;
+use args::Args;
+use asteroids::AsteroidBelt;
+use audio::{MusicPlayer, SoundEffect};
+use camera::{Camera, CameraAnimation, CameraMode};
+use color::{Color, ToneMap};
+use cubemap::Cubemap;
 use fastnoise_lite::FastNoiseLite;
-use framebuffer::Framebuffer;
+use fragment::Fragment;
+use framebuffer::{BlendMode, Framebuffer, Rect, RenderMode, ShadingMode};
+use hud::LabelMode;
+use input::Input;
 use obj::Obj;
-use ray_intersect::{RayIntersect, Sphere};
-use shaders::{fragment_shader, vertex_shader, ShaderType};
+use postprocess::MotionBlur;
+use ray_intersect::{spherical_uv, RayIntersect, Sphere, Triangle};
+use rayon::prelude::*;
+use rings::RingBands;
+use scene::{Body, OrbitState, Planet};
+use shaders::{
+    fragment_shader, in_eclipse, sun_direction, vertex_shader, ShaderType, ECLIPSE_LIGHT,
+};
+use uniforms::{project_to_screen, Uniforms};
 use vertex::Vertex;
 
-pub struct Uniforms {
-    model_matrix: Mat4,
-    view_matrix: Mat4,
-    projection_matrix: Mat4,
-    viewport_matrix: Mat4,
-    time: u32,
-    noise: FastNoiseLite,
+// Colisiones: se choca a menos de radio visible × margen + tamaño de la nave. Los
+// planetas llevan una capa proporcional a su tamaño; la luna se prueba contra el ojo de
+// la cámara, que solo necesita un pequeño hueco, así que se puede uno acercar a ella
+const SHIP_SIZE: f32 = 1.0;
+// Distancia a la que se dibuja la nave por delante de la cámara
+const SHIP_OFFSET: f32 = 15.0;
+const SUN_COLLISION_MARGIN: f32 = 1.1;
+const PLANET_COLLISION_MARGIN: f32 = 1.2;
+const MOON_COLLISION_MARGIN: f32 = 1.0;
+const MOON_SHIP_SIZE: f32 = 0.3;
+// Por debajo de este desplazamiento no merece la pena deslizarse por una superficie
+const MIN_SLIDE_DISTANCE: f32 = 1e-3;
+// Puntos del segmento de cada movimiento en los que se buscan choques
+const COLLISION_SUBSTEPS: u32 = 8;
+// Cuadros sin entrada antes de que empiece el modo de reposo (unos 30 segundos a 60 cuadros
+// por segundo). Se cuentan cuadros y no tiempo real para que una reproducción lo repita igual
+const IDLE_FRAMES: u32 = 1800;
+const IDLE_ORBIT_SPEED: f32 = 0.002;
+// Radianes de giro por píxel arrastrado con el botón derecho
+const MOUSE_LOOK_SENSITIVITY: f32 = 0.005;
+// Multiplicador de las velocidades de movimiento y zoom mientras se mantiene Shift izquierdo
+const BOOST_FACTOR: f32 = 5.0;
+// Fracción del zoom pendiente de la rueda que se aplica en cada cuadro, y por debajo de qué
+// cantidad se descarta
+const SCROLL_ZOOM_EASING: f32 = 0.3;
+const SCROLL_ZOOM_EPSILON: f32 = 1e-3;
+// Con la ventana en segundo plano solo se atienden sus eventos, a unas 5 actualizaciones por segundo
+const BACKGROUND_FRAME_DELAY: Duration = Duration::from_millis(200);
+// Con la simulación en pausa y la cámara quieta basta con unos 10 cuadros por segundo
+const IDLE_FRAME_DELAY: Duration = Duration::from_millis(100);
+// Tope del tiempo entre fotogramas para que nada salte al volver del segundo plano
+const MAX_FRAME_TIME: f32 = 0.1;
+const MUSIC_VOLUME_STEP: f32 = 0.05;
+// Vuelo hacia un planeta con las teclas 1-6: duración en cuadros (como el tiempo de la
+// simulación, para que una reproducción lo repita igual; unos 60 son un segundo), distancia
+// al planeta (más allá de la nave, que va por delante de la cámara) y elevación sobre su órbita
+const WARP_FRAMES: u32 = 60;
+const WARP_DISTANCE_RADII: f32 = 2.5;
+const WARP_ELEVATION: f32 = 0.35;
+// Mínimo del plano cercano adaptativo: por debajo se pierde demasiada precisión de profundidad
+const MIN_NEAR_PLANE: f32 = 0.01;
+// Frecuencia del ruido compartido por los shaders procedurales
+const NOISE_FREQUENCY: f32 = 0.01;
+// Radio en pantalla (píxeles) a partir del cual un asteroide se dibuja como roca con malla
+const ASTEROID_MESH_MIN_PIXELS: f32 = 3.0;
+// Modo de alambre (G): color de las aristas y cuánto se acercan en profundidad NDC
+const WIREFRAME_COLOR: u32 = 0x40FF80;
+const WIREFRAME_DEPTH_BIAS: f32 = 2e-5;
+// Distancia (en unidades del mundo) a lo largo de la cual una órbita se desvanece al
+// acercarse a la superficie que tiene delante
+const ORBIT_FADE_DISTANCE: f32 = 2.0;
+// Banda de distancia (más allá del radio de la órbita) en la que las órbitas aparecen
+// gradualmente al alejarse la cámara
+const ORBIT_VISIBILITY_FADE_START: f32 = 5.0;
+const ORBIT_VISIBILITY_FADE_END: f32 = 15.0;
+// Longitud (en unidades del mundo) de las flechas que indican la dirección de la luz
+const LIGHT_GIZMO_LENGTH: f32 = 8.0;
+// Valor de --planet-texture que genera un tablero de ajedrez en lugar de leer un archivo
+const CHECKER_TEXTURE: &str = "checker";
+// Multiplicador del brillo del fondo, independiente del sombreado de los cuerpos
+const SKYBOX_EXPOSURE_STEP: f32 = 0.1;
+const MAX_SKYBOX_EXPOSURE: f32 = 4.0;
+const AMBIENT_LIGHT_STEP: f32 = 0.05;
+// Resplandor: el umbral deja pasar las zonas calientes del sol y los brillos especulares
+// más intensos, no la cara iluminada de los planetas
+const BLOOM_THRESHOLD: f32 = 0.75;
+const BLOOM_RADIUS: usize = 12;
+const BLOOM_INTENSITY: f32 = 3.0;
+// Estelas: fracción del color del cuadro anterior que se conserva
+const TRAIL_FADE: f32 = 0.9;
+// Desenfoque de movimiento: unidades de desplazamiento que equivalen a un radián de giro
+const MOTION_BLUR_TURN_WEIGHT: f32 = 50.0;
+// Tras invertir el eje Y en el viewport, las caras frontales de los modelos quedan en
+// sentido horario en pantalla; se descartan las antihorarias
+const CULL_CLOCKWISE: bool = false;
+
+// Si hay choque devuelve la normal de contacto: la dirección del centro del cuerpo a la nave
+fn check_collision(
+    position: &Vec3,
+    target_position: &Vec3,
+    radius: f32,
+    ship_size: f32,
+    margin: f32,
+) -> Option<Vec3> {
+    let offset = position - target_position;
+    (offset.magnitude() < (radius * margin + ship_size))
+        .then(|| offset.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::y))
+}
+
+// Primer choque a lo largo del desplazamiento, probado en COLLISION_SUBSTEPS puntos del
+// segmento y no solo al final, para que un movimiento rápido no atraviese un cuerpo pequeño
+// de un cuadro al siguiente
+fn swept_contact(movement: Vec3, contact: &impl Fn(Vec3) -> Option<Vec3>) -> Option<Vec3> {
+    (1..=COLLISION_SUBSTEPS)
+        .find_map(|step| contact(movement * (step as f32 / COLLISION_SUBSTEPS as f32)))
+}
+
+// Respuesta a un choque: al movimiento bloqueado se le quita la componente que entra en la
+// superficie y se prueba de nuevo, para deslizarse por ella en lugar de detenerse. `contact`
+// da la normal de contacto tras un desplazamiento. Devuelve el desplazamiento permitido (nulo
+// si tampoco cabe el deslizamiento) y si hubo choque
+fn slide_movement(movement: Vec3, contact: impl Fn(Vec3) -> Option<Vec3>) -> (Vec3, bool) {
+    let Some(normal) = swept_contact(movement, &contact) else {
+        return (movement, false);
+    };
+    let slide = movement - normal * movement.dot(&normal).min(0.0);
+    if slide.magnitude() > MIN_SLIDE_DISTANCE && swept_contact(slide, &contact).is_none() {
+        (slide, true)
+    } else {
+        (Vec3::zeros(), true)
+    }
+}
+
+// Distancia desde la posición hasta la superficie del cuerpo más cercano, con los mismos
+// radios visibles que usan las colisiones
+fn nearest_surface_distance(
+    position: &Vec3,
+    planets: &[Planet],
+    planet_positions: &[Vec3],
+    moon_position: &Vec3,
+    sphere_radius: f32,
+    moon_radius: f32,
+) -> f32 {
+    let sun = (position - Vec3::new(0.0, 0.0, 0.0)).magnitude() - sphere_radius * scene::SUN_SCALE;
+    let moon = (position - moon_position).magnitude() - moon_radius * scene::MOON_SCALE;
+    planet_positions
+        .iter()
+        .zip(planets)
+        .map(|(center, planet)| (position - center).magnitude() - sphere_radius * planet.scale)
+        .fold(sun.min(moon), f32::min)
+}
+
+// El plano cercano se acerca a la mitad de la distancia a la superficie más próxima, sin
+// pasar del configurado
+fn adaptive_near_plane(surface_distance: f32, max_near: f32) -> f32 {
+    (surface_distance * 0.5).clamp(MIN_NEAR_PLANE.min(max_near), max_near)
 }
 
-fn check_collision(position: &Vec3, target_position: &Vec3, radius: f32) -> bool {
-    let distance = (position - target_position).magnitude();
-    let safety_margin = 1.0;
-    let ship_size = 1.0;
-    distance < (radius * safety_margin + ship_size)
+// Triángulos en el espacio del mundo de cada cuerpo, en el orden de scene::bodies. El giro
+// sobre su eje no se aplica: las mallas son esferas y no cambia dónde se puede hacer clic
+fn body_triangles(world: &World, orbits: &OrbitState, time: u32) -> Vec<Vec<Triangle>> {
+    let planets = orbits.planets();
+    scene::bodies(planets)
+        .map(|body| {
+            let position = body.position(orbits, time);
+            let model_matrix = create_model_matrix(position, body.scale(planets), 0.0);
+            let mesh = if body == Body::Moon {
+                &world.moon
+            } else {
+                &world.sphere
+            };
+            mesh.chunks_exact(3)
+                .map(|tri| {
+                    let [a, b, c] = [0, 1, 2].map(|i| {
+                        let p = tri[i].position;
+                        (model_matrix * Vec4::new(p.x, p.y, p.z, 1.0)).xyz()
+                    });
+                    Triangle::new(a, b, c)
+                })
+                .collect()
+        })
+        .collect()
+}
+
+// Lanza un rayo desde la cámara por el punto (ndc_x, ndc_y) de la pantalla y devuelve el
+// índice del objeto con el impacto más cercano
+fn pick(
+    view_matrix: &Mat4,
+    projection_matrix: &Mat4,
+    ndc_x: f32,
+    ndc_y: f32,
+    objects: &[Vec<Triangle>],
+    cull_back_faces: bool,
+) -> Option<usize> {
+    let inverse = (projection_matrix * view_matrix).try_inverse()?;
+    let unproject = |ndc_z: f32| {
+        let point = inverse * Vec4::new(ndc_x, ndc_y, ndc_z, 1.0);
+        point.xyz() / point.w
+    };
+    let origin = unproject(-1.0);
+    let direction = (unproject(1.0) - origin).normalize();
+
+    objects
+        .iter()
+        .enumerate()
+        .filter_map(|(index, triangles)| {
+            triangles
+                .iter()
+                .map(|triangle| triangle.intersect(&origin, &direction, cull_back_faces))
+                .filter(|hit| hit.hit)
+                .map(|hit| hit.distance)
+                .min_by(f32::total_cmp)
+                .map(|distance| (index, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(index, _)| index)
 }
 
 fn is_in_frustum(
@@ -68,15 +279,43 @@ fn is_in_frustum(
 }
 
 fn create_model_matrix(translation: Vec3, scale: f32, rotation_angle: f32) -> Mat4 {
+    create_tilted_model_matrix(translation, scale, 0.0, rotation_angle)
+}
+
+// Traslación * inclinación del eje (grados, alrededor de X) * giro sobre el eje * escala.
+// El giro va antes que la inclinación, así que el cuerpo gira sobre su eje ya inclinado
+fn create_tilted_model_matrix(
+    translation: Vec3,
+    scale: f32,
+    tilt: f32,
+    rotation_angle: f32,
+) -> Mat4 {
     Mat4::new_translation(&translation)
+        * Mat4::from_axis_angle(&Vec3::x_axis(), tilt.to_radians())
         * Mat4::from_axis_angle(&Vec3::y_axis(), rotation_angle)
         * Mat4::new_scaling(scale)
 }
 
-fn create_perspective_matrix(window_width: f32, window_height: f32) -> Mat4 {
-    let fov = 75.0 * PI / 180.0;
+fn create_perspective_matrix(
+    window_width: f32,
+    window_height: f32,
+    fov_deg: f32,
+    near: f32,
+    far: f32,
+) -> Mat4 {
     let aspect_ratio = window_width / window_height;
-    perspective(fov, aspect_ratio, 0.1, 1000.0)
+    perspective(fov_deg.to_radians(), aspect_ratio, near, far)
+}
+
+// Convierte una profundidad NDC en distancia a la cámara, para comparar en unidades del mundo.
+// Se lee de la propia proyección porque el plano cercano puede cambiar entre cuadros.
+fn linear_depth(ndc_z: f32, projection_matrix: &Mat4) -> f32 {
+    projection_matrix[(2, 3)] / (ndc_z + projection_matrix[(2, 2)])
+}
+
+// Distancia del plano cercano de una proyección (la profundidad lineal de NDC z = -1)
+fn near_plane(projection_matrix: &Mat4) -> f32 {
+    linear_depth(-1.0, projection_matrix)
 }
 
 fn create_viewport_matrix(width: f32, height: f32) -> Mat4 {
@@ -104,12 +343,15 @@ fn render_skybox(
     framebuffer: &mut Framebuffer,
     camera: &Camera,
     skybox_texture: &Texture,
+    skybox_cubemap: Option<&Cubemap>,
+    seam_offset: f32,
+    exposure: f32,
     uniforms: &Uniforms,
 ) {
     let width = framebuffer.width as f32;
     let height = framebuffer.height as f32;
 
-    let sky_sphere = Sphere::new(camera.eye, 2000.0);
+    let sky_sphere = Sphere::new(camera.eye, 2000.0).with_uv_offset(seam_offset);
     for y in 0..framebuffer.height {
         for x in 0..framebuffer.width {
             let ndc_x = (x as f32 / width) * 2.0 - 1.0;
@@ -117,11 +359,17 @@ fn render_skybox(
             let ray_dir = uniforms.projection_matrix * Vec4::new(ndc_x, ndc_y, 1.0, 0.0);
             let ray_direction = (ray_dir.xyz()).normalize();
 
+            if let Some(cubemap) = skybox_cubemap {
+                framebuffer.set_current_color((cubemap.sample(&ray_direction) * exposure).to_hex());
+                framebuffer.point(x, y, f32::MAX);
+                continue;
+            }
+
             let intersect = sky_sphere.ray_intersect(&camera.eye, &ray_direction);
 
             if intersect.hit {
-                let color = skybox_texture.get_color(intersect.uv.0, intersect.uv.1);
-                framebuffer.set_current_color(color.to_hex());
+                let color = skybox_texture.get_color_bilinear(intersect.uv.0, intersect.uv.1);
+                framebuffer.set_current_color((color * exposure).to_hex());
                 framebuffer.point(x, y, f32::MAX);
             }
         }
@@ -133,6 +381,7 @@ fn render(
     uniforms: &Uniforms,
     vertex_array: &[Vertex],
     shader_type: &ShaderType,
+    cull_clockwise: bool,
 ) {
     let mut transformed_vertices = Vec::with_capacity(vertex_array.len());
     for vertex in vertex_array {
@@ -140,9 +389,20 @@ fn render(
         transformed_vertices.push(transformed);
     }
 
+    // Sin ordenar: el z-buffer decide qué fragmento queda delante
     let mut triangles = Vec::new();
     for i in (0..transformed_vertices.len()).step_by(3) {
         if i + 2 < transformed_vertices.len() {
+            // Se descartan las caras traseras: las que en pantalla tienen la orientación
+            // indicada por cull_clockwise
+            let (a, b, c) = (
+                &transformed_vertices[i],
+                &transformed_vertices[i + 1],
+                &transformed_vertices[i + 2],
+            );
+            if triangle::is_clockwise(a, b, c) == cull_clockwise {
+                continue;
+            }
             triangles.push([
                 transformed_vertices[i].clone(),
                 transformed_vertices[i + 1].clone(),
@@ -151,32 +411,365 @@ fn render(
         }
     }
 
-    // Ordenar triángulos por profundidad (back-to-front)
-    triangles.sort_by(|a, b| {
-        let z_a = (a[0].position.z + a[1].position.z + a[2].position.z) / 3.0;
-        let z_b = (b[0].position.z + b[1].position.z + b[2].position.z) / 3.0;
-        z_b.partial_cmp(&z_a).unwrap_or(std::cmp::Ordering::Equal)
-    });
+    // La rasterización y el sombreado van en paralelo; el orden de los fragmentos se
+    // conserva al recogerlos, así que el resultado no depende del reparto entre hilos.
+    // La intensidad difusa viene del sol, así que el terminador gira con la órbita
+    // Los bordes parciales solo se generan para caras opacas y rellenas: en las translúcidas
+    // las aristas compartidas se mezclarían dos veces
+    let light_dir = sun_direction(uniforms);
+    let shading = framebuffer.shading_mode;
+    let edge_aa = framebuffer.edge_antialiasing
+        && framebuffer.blend_mode() == BlendMode::Opaque
+        && framebuffer.render_mode == RenderMode::Filled
+        && framebuffer.overdraw.is_none();
+    let fragments: Vec<Fragment> = triangles
+        .par_iter()
+        .flat_map_iter(|tri| {
+            triangle::triangle(&tri[0], &tri[1], &tri[2], &light_dir, shading, edge_aa)
+        })
+        .collect();
+
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let on_screen = |fragment: &Fragment| {
+        let x = fragment.position.x as usize;
+        let y = fragment.position.y as usize;
+        (x < width && y < height && framebuffer.in_scissor(x, y)).then_some((x, y))
+    };
+
+    // En el modo de sobredibujado solo se cuentan los fragmentos, sin sombrearlos
+    if framebuffer.overdraw.is_some() {
+        let pixels: Vec<(usize, usize)> = fragments.iter().filter_map(on_screen).collect();
+        if let Some(overdraw) = &mut framebuffer.overdraw {
+            for (x, y) in pixels {
+                overdraw[y * width + x] += 1;
+            }
+        }
+        return;
+    }
 
-    let mut fragments = Vec::new();
-    for tri in &triangles {
-        fragments.extend(triangle::triangle(&tri[0], &tri[1], &tri[2]));
+    if framebuffer.render_mode == RenderMode::Wireframe {
+        // Las caras se rellenan solo en el z-buffer (las translúcidas no, como al sombrear)
+        // para que tapen las aristas que quedan detrás de ellas
+        if framebuffer.blend_mode() == BlendMode::Opaque {
+            let pixels: Vec<(usize, f32)> = fragments
+                .iter()
+                .filter_map(|fragment| {
+                    let (x, y) = on_screen(fragment)?;
+                    Some((y * width + x, fragment.depth))
+                })
+                .collect();
+            for (index, depth) in pixels {
+                if depth < framebuffer.zbuffer[index] {
+                    framebuffer.zbuffer[index] = depth;
+                }
+            }
+        }
+        render_wireframe(framebuffer, &triangles);
+        return;
     }
 
-    for fragment in fragments {
-        let x = fragment.position.x as usize;
-        let y = fragment.position.y as usize;
+    // Se sombrean sólo los fragmentos que quedan por delante de lo ya dibujado
+    let zbuffer = &framebuffer.zbuffer;
+    let shaded: Vec<(usize, usize, f32, Color, f32)> = fragments
+        .into_par_iter()
+        .filter_map(|mut fragment| {
+            let (x, y) = on_screen(&fragment)?;
+            if fragment.depth >= zbuffer[y * width + x] {
+                return None;
+            }
+            if uniforms.emissive {
+                fragment.intensity = 1.0;
+            } else {
+                if in_eclipse(&fragment, uniforms) {
+                    fragment.intensity *= ECLIPSE_LIGHT;
+                }
+                // La luz ambiente pone el piso: la cara nocturna no queda del todo negra
+                fragment.intensity =
+                    uniforms.ambient + (1.0 - uniforms.ambient) * fragment.intensity;
+            }
+            let color = fragment_shader(&fragment, uniforms, shader_type);
+            Some((x, y, fragment.depth, color, fragment.coverage))
+        })
+        .collect();
+
+    // Escritura en serie: gana el fragmento más cercano y, a igual profundidad, el primero.
+    // El canal a del color sólo cuenta si el framebuffer está en modo de mezcla. Un borde
+    // parcial se mezcla según su cobertura sin ocupar el z-buffer, así que el triángulo
+    // vecino que cubra el centro del píxel lo reemplaza entero y no quedan costuras
+    for (x, y, depth, color, coverage) in shaded {
+        framebuffer.set_current_color(color.to_hex());
+        if coverage < 1.0 {
+            framebuffer.set_blend_mode(BlendMode::AlphaBlend);
+            framebuffer.set_current_alpha(coverage);
+            framebuffer.point(x, y, depth);
+            framebuffer.set_blend_mode(BlendMode::Opaque);
+        } else {
+            framebuffer.set_current_alpha(color.a as f32 / 255.0);
+            framebuffer.point(x, y, depth);
+        }
+    }
+}
+
+// Aristas de los triángulos ya transformados, con prueba de profundidad. Se acercan un poco
+// a la cámara para que no las tape el relleno de profundidad de su propia cara; las que
+// tienen un vértice detrás de la cámara se omiten y el resto se recorta a la pantalla
+fn render_wireframe(framebuffer: &mut Framebuffer, triangles: &[[Vertex; 3]]) {
+    framebuffer.set_current_color(WIREFRAME_COLOR);
+    framebuffer.set_current_alpha(1.0);
+    let (width, height) = (framebuffer.width as f32, framebuffer.height as f32);
+
+    for tri in triangles {
+        for (a, b) in [(&tri[0], &tri[1]), (&tri[1], &tri[2]), (&tri[2], &tri[0])] {
+            if a.clip_w <= 0.0 || b.clip_w <= 0.0 {
+                continue;
+            }
+            let Some((start, end)) =
+                clip_segment(a.transformed_position, b.transformed_position, width, height)
+            else {
+                continue;
+            };
+            line_with_depth(
+                framebuffer,
+                start.x as usize,
+                start.y as usize,
+                end.x as usize,
+                end.y as usize,
+                start.z - WIREFRAME_DEPTH_BIAS,
+                end.z - WIREFRAME_DEPTH_BIAS,
+            );
+        }
+    }
+}
+
+// Recorta el segmento de pantalla a [0, width) x [0, height) (Liang-Barsky), interpolando
+// también la profundidad. None si queda entero fuera
+fn clip_segment(start: Vec3, end: Vec3, width: f32, height: f32) -> Option<(Vec3, Vec3)> {
+    let delta = end - start;
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    let max_x = width - 1.0;
+    let max_y = height - 1.0;
+    for (p, q) in [
+        (-delta.x, start.x),
+        (delta.x, max_x - start.x),
+        (-delta.y, start.y),
+        (delta.y, max_y - start.y),
+    ] {
+        if p == 0.0 {
+            if q < 0.0 {
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    (t0 <= t1).then(|| (start + delta * t0, start + delta * t1))
+}
+
+// Cuerpo pendiente de dibujar, con el radio de su esfera envolvente (centrada en
+// uniforms.center) para acotarlo en pantalla
+struct BodyDraw<'a> {
+    uniforms: Uniforms<'a>,
+    vertices: &'a [Vertex],
+    shader: ShaderType,
+    radius: f32,
+}
+
+// Radio de la esfera centrada en el origen que contiene todos los vértices del modelo
+fn bounding_radius(vertices: &[Vertex]) -> f32 {
+    vertices
+        .iter()
+        .map(|vertex| vertex.position.magnitude())
+        .fold(0.0, f32::max)
+}
+
+// Sustituye las UV del modelo por coordenadas esféricas (el sphere.obj trae cada triángulo
+// mapeado a la textura completa). En los triángulos que cruzan la costura se suma 1 a las
+// u pequeñas para no interpolar a través de toda la textura, y en los polos se usa la u
+// media de los otros dos vértices.
+fn apply_spherical_uvs(vertices: &mut [Vertex], longitude_offset: f32) {
+    for tri in vertices.chunks_exact_mut(3) {
+        let mut uvs: Vec<(f32, f32)> = tri
+            .iter()
+            .map(|vertex| spherical_uv(&vertex.position.normalize(), longitude_offset))
+            .collect();
+
+        let max_u = uvs.iter().map(|uv| uv.0).fold(0.0, f32::max);
+        for uv in uvs.iter_mut() {
+            if max_u - uv.0 > 0.5 {
+                uv.0 += 1.0;
+            }
+        }
+        for i in 0..3 {
+            if tri[i].position.normalize().y.abs() > 0.999 {
+                uvs[i].0 = (uvs[(i + 1) % 3].0 + uvs[(i + 2) % 3].0) / 2.0;
+            }
+        }
+
+        for (vertex, (u, v)) in tri.iter_mut().zip(uvs) {
+            vertex.tex_coords = Vec2::new(u, v);
+        }
+    }
+}
+
+// Rectángulo de pantalla que cubre la esfera envolvente, o None si queda fuera o detrás
+// de la cámara. Se proyectan las esquinas del cubo que contiene la esfera; si el cubo cruza
+// el plano cercano se usa toda la pantalla.
+fn screen_bounds(
+    center: &Vec3,
+    radius: f32,
+    uniforms: &Uniforms,
+    width: usize,
+    height: usize,
+) -> Option<Rect> {
+    let view_projection = uniforms.projection_matrix * uniforms.view_matrix;
+    let depth = (view_projection * Vec4::new(center.x, center.y, center.z, 1.0)).w;
+    let extent = radius * 3.0_f32.sqrt();
+    let near = near_plane(&uniforms.projection_matrix);
+    if depth + extent <= near {
+        return None;
+    }
+    if depth - extent <= near {
+        return Some(Rect {
+            x: 0,
+            y: 0,
+            width,
+            height,
+        });
+    }
+
+    let mut min = Vec2::new(f32::MAX, f32::MAX);
+    let mut max = Vec2::new(f32::MIN, f32::MIN);
+    for &dx in &[-1.0, 1.0] {
+        for &dy in &[-1.0, 1.0] {
+            for &dz in &[-1.0, 1.0] {
+                let corner = center + Vec3::new(dx, dy, dz) * radius;
+                let clip = view_projection * Vec4::new(corner.x, corner.y, corner.z, 1.0);
+                let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, 0.0, 1.0);
+                let screen = (uniforms.viewport_matrix * ndc).xy();
+                min = min.inf(&screen);
+                max = max.sup(&screen);
+            }
+        }
+    }
+
+    // Margen para el redondeo de la rasterización
+    let min_x = (min.x - 1.0).max(0.0);
+    let min_y = (min.y - 1.0).max(0.0);
+    let max_x = (max.x + 2.0).min(width as f32);
+    let max_y = (max.y + 2.0).min(height as f32);
+    if min_x >= max_x || min_y >= max_y {
+        return None;
+    }
+    Some(Rect {
+        x: min_x as usize,
+        y: min_y as usize,
+        width: max_x as usize - min_x as usize,
+        height: max_y as usize - min_y as usize,
+    })
+}
+
+// Dibuja cada cuerpo en paralelo sobre su propio tile y luego los combina en serie sobre
+// el framebuffer principal respetando el z-buffer
+fn render_bodies_parallel(framebuffer: &mut Framebuffer, bodies: &[BodyDraw]) {
+    let (width, height) = (framebuffer.width, framebuffer.height);
+    let shading_mode = framebuffer.shading_mode;
+    let tiles: Vec<(Rect, Framebuffer)> = bodies
+        .par_iter()
+        .filter_map(|body| {
+            let center = body.uniforms.center;
+            let bounds = screen_bounds(&center, body.radius, &body.uniforms, width, height)?;
+            let mut tile = Framebuffer::new(bounds.width, bounds.height);
+            tile.shading_mode = shading_mode;
+            let tile_uniforms = Uniforms {
+                viewport_matrix: Mat4::new_translation(&Vec3::new(
+                    -(bounds.x as f32),
+                    -(bounds.y as f32),
+                    0.0,
+                )) * body.uniforms.viewport_matrix,
+                ..body.uniforms
+            };
+            render(&mut tile, &tile_uniforms, body.vertices, &body.shader, CULL_CLOCKWISE);
+            Some((bounds, tile))
+        })
+        .collect();
+
+    for (bounds, tile) in &tiles {
+        for ty in 0..tile.height {
+            for tx in 0..tile.width {
+                let tile_index = ty * tile.width + tx;
+                let depth = tile.zbuffer[tile_index];
+                let (x, y) = (bounds.x + tx, bounds.y + ty);
+                let index = y * width + x;
+                if depth < framebuffer.zbuffer[index] && framebuffer.in_scissor(x, y) {
+                    framebuffer.buffer[index] = tile.buffer[tile_index];
+                    framebuffer.zbuffer[index] = depth;
+                }
+            }
+        }
+    }
+}
 
-        if x < framebuffer.width && y < framebuffer.height {
-            let z_index = y * framebuffer.width + x;
+// Igual que line_with_depth, pero los píxeles cercanos a la profundidad ya guardada se
+// desvanecen en lugar de cortarse de golpe detrás de la silueta de un cuerpo. Con opacidad
+// menor que 1 la línea se mezcla con el fondo y no escribe en el z-buffer.
+#[allow(clippy::too_many_arguments)]
+fn line_with_depth_fade(
+    framebuffer: &mut Framebuffer,
+    x1: usize,
+    y1: usize,
+    x2: usize,
+    y2: usize,
+    z1: f32,
+    z2: f32,
+    opacity: f32,
+    projection_matrix: &Mat4,
+) {
+    let dx = (x2 as i32) - (x1 as i32);
+    let dy = (y2 as i32) - (y1 as i32);
 
-            if fragment.depth <= framebuffer.zbuffer[z_index] + 0.0001 {
-                let shaded_color = fragment_shader(&fragment, uniforms, shader_type);
-                framebuffer.set_current_color(shaded_color.to_hex());
-                framebuffer.point(x, y, fragment.depth);
-                framebuffer.zbuffer[z_index] = fragment.depth;
+    let steps = dx.abs().max(dy.abs());
+    if steps == 0 {
+        return;
+    }
+
+    let x_inc = dx as f32 / steps as f32;
+    let y_inc = dy as f32 / steps as f32;
+    let z_inc = (z2 - z1) / steps as f32;
+
+    let mut x = x1 as f32;
+    let mut y = y1 as f32;
+    let mut z = z1;
+
+    for _ in 0..=steps {
+        let px = x as usize;
+        let py = y as usize;
+
+        if px < framebuffer.width && py < framebuffer.height && framebuffer.in_scissor(px, py) {
+            let stored = framebuffer.zbuffer[py * framebuffer.width + px];
+            // El skybox deja f32::MAX en el z-buffer: cuenta como fondo, igual que INFINITY
+            let fade = if stored < f32::MAX {
+                (linear_depth(stored, projection_matrix) - linear_depth(z, projection_matrix))
+                    / ORBIT_FADE_DISTANCE
+            } else {
+                1.0
+            };
+            let fade = fade.min(1.0) * opacity;
+
+            if fade >= 1.0 {
+                framebuffer.point(px, py, z);
+            } else if fade > 0.0 {
+                framebuffer.blend_point(px, py, fade);
             }
         }
+
+        x += x_inc;
+        y += y_inc;
+        z += z_inc;
     }
 }
 
@@ -209,7 +802,11 @@ fn line_with_depth(
         let px = x as usize;
         let py = y as usize;
 
-        if px < framebuffer.width && py < framebuffer.height {
+        // Solo se traza si la línea queda delante de lo ya dibujado (planetas, anillos)
+        if px < framebuffer.width
+            && py < framebuffer.height
+            && z < framebuffer.zbuffer[py * framebuffer.width + px]
+        {
             framebuffer.point(px, py, z);
         }
 
@@ -219,6 +816,60 @@ fn line_with_depth(
     }
 }
 
+// Línea de Xiaolin Wu: en cada columna (o fila, si la línea es empinada) la cobertura se
+// reparte entre los dos píxeles más cercanos y se mezcla con el color que ya hay. Solo se
+// dibuja delante de lo que ocupa el z-buffer, y sin escribir profundidad. El último píxel
+// queda para el segmento siguiente, así las uniones de una órbita no se mezclan dos veces
+#[allow(clippy::too_many_arguments)]
+fn line_aa(
+    framebuffer: &mut Framebuffer,
+    x1: f32,
+    y1: f32,
+    x2: f32,
+    y2: f32,
+    z1: f32,
+    z2: f32,
+    color: u32,
+    opacity: f32,
+) {
+    let steep = (y2 - y1).abs() > (x2 - x1).abs();
+    // En líneas empinadas se recorre y como eje principal
+    let (a1, b1, a2, b2) = if steep { (y1, x1, y2, x2) } else { (x1, y1, x2, y2) };
+
+    let start = a1.floor();
+    let steps = (a2.floor() - start) as i32;
+    let direction = steps.signum();
+
+    let mut plot = |a: f32, b: f32, depth: f32, coverage: f32| {
+        let (x, y) = if steep { (b, a) } else { (a, b) };
+        if x < 0.0 || y < 0.0 {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        if x < framebuffer.width
+            && y < framebuffer.height
+            && framebuffer.in_scissor(x, y)
+            && depth < framebuffer.zbuffer[y * framebuffer.width + x]
+        {
+            framebuffer.blend_pixel(x, y, color, coverage * opacity);
+        }
+    };
+
+    for i in 0..steps.abs() {
+        let a = start + (i * direction) as f32;
+        // Se muestrea en el centro del píxel
+        let t = ((a + 0.5 - a1) / (a2 - a1)).clamp(0.0, 1.0);
+        let b = b1 + (b2 - b1) * t - 0.5;
+        let depth = z1 + (z2 - z1) * t;
+
+        let b_floor = b.floor();
+        let fraction = b - b_floor;
+        plot(a, b_floor, depth, 1.0 - fraction);
+        plot(a, b_floor + 1.0, depth, fraction);
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn line_with_thickness(
     framebuffer: &mut Framebuffer,
     x1: usize,
@@ -228,6 +879,8 @@ fn line_with_thickness(
     z1: f32,
     z2: f32,
     thickness: f32,
+    opacity: f32,
+    projection_matrix: &Mat4,
 ) {
     let dx = (x2 as f32) - (x1 as f32);
     let dy = (y2 as f32) - (y1 as f32);
@@ -241,7 +894,7 @@ fn line_with_thickness(
     let dx = dx / distance;
     let dy = dy / distance;
 
-    line_with_depth(framebuffer, x1, y1, x2, y2, z1, z2);
+    line_with_depth_fade(framebuffer, x1, y1, x2, y2, z1, z2, opacity, projection_matrix);
 
     if thickness <= 1.0 {
         return;
@@ -263,7 +916,7 @@ fn line_with_thickness(
             && x2_offset < framebuffer.width
             && y2_offset < framebuffer.height
         {
-            line_with_depth(
+            line_with_depth_fade(
                 framebuffer,
                 x1_offset,
                 y1_offset,
@@ -271,6 +924,8 @@ fn line_with_thickness(
                 y2_offset,
                 z1,
                 z2,
+                opacity,
+                projection_matrix,
             );
         }
 
@@ -284,7 +939,7 @@ fn line_with_thickness(
             && x2_offset < framebuffer.width
             && y2_offset < framebuffer.height
         {
-            line_with_depth(
+            line_with_depth_fade(
                 framebuffer,
                 x1_offset,
                 y1_offset,
@@ -292,18 +947,23 @@ fn line_with_thickness(
                 y2_offset,
                 z1,
                 z2,
+                opacity,
+                projection_matrix,
             );
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn render_orbit_lines(
     framebuffer: &mut Framebuffer,
-    orbit_radius: f32,
+    planet: &Planet,
     color: Color,
     segments: usize,
     uniforms: &Uniforms,
     visibility_factor: f32,
+    opacity: f32,
+    antialias: bool,
 ) {
     let line_thickness = 0.001 * visibility_factor.max(0.1); 
 
@@ -320,19 +980,11 @@ fn render_orbit_lines(
         let angle1 = 2.0 * PI * (i as f32) / (segments as f32);
         let angle2 = 2.0 * PI * ((i + 1) as f32) / (segments as f32);
 
-        // Posiciones en el espacio 3D
-        let world_pos1 = Vec4::new(
-            orbit_radius * angle1.cos(),
-            -0.01,
-            orbit_radius * angle1.sin(),
-            1.0,
-        );
-        let world_pos2 = Vec4::new(
-            orbit_radius * angle2.cos(),
-            -0.02,
-            orbit_radius * angle2.sin(),
-            1.0,
-        );
+        // Posiciones en el espacio 3D, sobre la misma elipse que recorre el planeta
+        let point1 = planet.orbit_point(angle1);
+        let point2 = planet.orbit_point(angle2);
+        let world_pos1 = Vec4::new(point1.x, point1.y - 0.01, point1.z, 1.0);
+        let world_pos2 = Vec4::new(point2.x, point2.y - 0.02, point2.z, 1.0);
 
         let clip_pos1 = uniforms.projection_matrix * uniforms.view_matrix * world_pos1;
         let clip_pos2 = uniforms.projection_matrix * uniforms.view_matrix * world_pos2;
@@ -364,6 +1016,20 @@ fn render_orbit_lines(
             && screen_x2 < framebuffer.width
             && screen_y2 < framebuffer.height
         {
+            if antialias {
+                line_aa(
+                    framebuffer,
+                    screen_pos1.x,
+                    screen_pos1.y,
+                    screen_pos2.x,
+                    screen_pos2.y,
+                    ndc_pos1.z,
+                    ndc_pos2.z,
+                    adjusted_color.to_hex(),
+                    opacity,
+                );
+                continue;
+            }
             line_with_thickness(
                 framebuffer,
                 screen_x1,
@@ -372,12 +1038,185 @@ fn render_orbit_lines(
                 screen_y2,
                 ndc_pos1.z,
                 ndc_pos2.z,
-                line_thickness, 
+                line_thickness,
+                opacity,
+                &uniforms.projection_matrix,
             );
         }
     }
 }
 
+// Dibuja una línea 3D partida en tramos para poder descartar los que salen de pantalla
+fn render_world_line(
+    framebuffer: &mut Framebuffer,
+    start: &Vec3,
+    end: &Vec3,
+    segments: usize,
+    uniforms: &Uniforms,
+) {
+    for i in 0..segments {
+        let p1 = start.lerp(end, i as f32 / segments as f32);
+        let p2 = start.lerp(end, (i + 1) as f32 / segments as f32);
+
+        if let (Some(s1), Some(s2)) = (
+            project_to_screen(&p1, uniforms),
+            project_to_screen(&p2, uniforms),
+        ) {
+            let on_screen = |s: &Vec3| {
+                s.x >= 0.0
+                    && s.y >= 0.0
+                    && (s.x as usize) < framebuffer.width
+                    && (s.y as usize) < framebuffer.height
+            };
+            if on_screen(&s1) && on_screen(&s2) {
+                line_with_depth(
+                    framebuffer,
+                    s1.x as usize,
+                    s1.y as usize,
+                    s2.x as usize,
+                    s2.y as usize,
+                    s1.z,
+                    s2.z,
+                );
+            }
+        }
+    }
+}
+
+// Dibuja el frustum de una cámara secundaria a partir de la inversa de su view-projection
+fn render_frustum(framebuffer: &mut Framebuffer, view_projection: &Mat4, uniforms: &Uniforms) {
+    let inverse = match view_projection.try_inverse() {
+        Some(inverse) => inverse,
+        None => return,
+    };
+
+    let mut corners = Vec::with_capacity(8);
+    for &z in &[-1.0, 1.0] {
+        for &(x, y) in &[(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+            let world = inverse * Vec4::new(x, y, z, 1.0);
+            corners.push(Vec3::new(world.x / world.w, world.y / world.w, world.z / world.w));
+        }
+    }
+
+    let edges = [
+        (0, 1), (1, 2), (2, 3), (3, 0), // plano cercano
+        (4, 5), (5, 6), (6, 7), (7, 4), // plano lejano
+        (0, 4), (1, 5), (2, 6), (3, 7), // aristas laterales
+    ];
+
+    framebuffer.set_current_color(0xFFFF00);
+    for &(a, b) in &edges {
+        render_world_line(framebuffer, &corners[a], &corners[b], 64, uniforms);
+    }
+}
+
+// Cinturón de asteroides: se descarta entero si su caja queda fuera de la vista y luego
+// asteroide por asteroide. Los que ocupan pocos píxeles se dibujan como un cuadrado; los
+// cercanos, como rocas con el shader rocoso y la malla de la luna, que comparten todos (solo
+// cambia la matriz de modelo de cada uno)
+fn render_asteroid_belt(
+    framebuffer: &mut Framebuffer,
+    world: &World,
+    uniforms: &Uniforms,
+    view_projection: &Mat4,
+) {
+    let belt = &world.asteroid_belt;
+    if !belt.bounds().is_in_frustum(view_projection) {
+        return;
+    }
+
+    for asteroid in belt.asteroids() {
+        let position = asteroid.position(uniforms.time);
+        if !is_in_frustum(
+            &position,
+            asteroid.size,
+            &uniforms.view_matrix,
+            &uniforms.projection_matrix,
+        ) {
+            continue;
+        }
+        let Some((screen, radius)) = asteroids::project(asteroid, uniforms) else {
+            continue;
+        };
+        if radius < ASTEROID_MESH_MIN_PIXELS {
+            asteroids::render_splat(framebuffer, &screen, radius);
+            continue;
+        }
+
+        let rock_uniforms = Uniforms {
+            model_matrix: asteroid.transform(uniforms.time),
+            center: position,
+            occluder: None,
+            emissive: false,
+            ..*uniforms
+        };
+        render(
+            framebuffer,
+            &rock_uniforms,
+            &world.moon,
+            &ShaderType::RockyPlanet,
+            CULL_CLOCKWISE,
+        );
+    }
+}
+
+// Flecha desde la superficie de cada planeta visible hacia el sol: indica hacia dónde mira
+// el lado diurno. Usa líneas con prueba de profundidad, así que los cuerpos las tapan.
+fn render_light_gizmos(
+    framebuffer: &mut Framebuffer,
+    orbits: &OrbitState,
+    sphere_radius: f32,
+    uniforms: &Uniforms,
+) {
+    framebuffer.set_current_color(0xFFD040);
+    for (i, planet) in orbits.planets().iter().enumerate() {
+        let scale = planet.scale;
+        let center = orbits.planet_position(i, uniforms.time);
+        if !is_in_frustum(
+            &center,
+            scale + LIGHT_GIZMO_LENGTH,
+            &uniforms.view_matrix,
+            &uniforms.projection_matrix,
+        ) {
+            continue;
+        }
+
+        let light_dir = (Vec3::new(0.0, 0.0, 0.0) - center).normalize();
+        let start = center + light_dir * sphere_radius * scale;
+        let tip = start + light_dir * LIGHT_GIZMO_LENGTH;
+        let side = light_dir.cross(&Vec3::y()).normalize();
+        let back = tip - light_dir * LIGHT_GIZMO_LENGTH * 0.2;
+
+        render_world_line(framebuffer, &start, &tip, 16, uniforms);
+        render_world_line(framebuffer, &tip, &(back + side), 4, uniforms);
+        render_world_line(framebuffer, &tip, &(back - side), 4, uniforms);
+    }
+}
+
+fn print_matrix(name: &str, matrix: &Mat4) {
+    println!("{}:", name);
+    for row in 0..4 {
+        println!(
+            "  [{:>10.4} {:>10.4} {:>10.4} {:>10.4}]",
+            matrix[(row, 0)],
+            matrix[(row, 1)],
+            matrix[(row, 2)],
+            matrix[(row, 3)]
+        );
+    }
+}
+
+// Vuelca al stdout las matrices y el estado de la cámara del cuadro actual
+fn print_debug_state(camera: &Camera, uniforms: &Uniforms) {
+    println!("--- Estado del cuadro (time = {}) ---", uniforms.time);
+    println!("eye:    {:?}", camera.eye.as_slice());
+    println!("center: {:?}", camera.center.as_slice());
+    println!("up:     {:?}", camera.up.as_slice());
+    print_matrix("view_matrix", &uniforms.view_matrix);
+    print_matrix("projection_matrix", &uniforms.projection_matrix);
+    print_matrix("viewport_matrix", &uniforms.viewport_matrix);
+}
+
 fn calculate_visibility_factor(distance: f32, min_dist: f32, max_dist: f32) -> f32 {
     if distance < min_dist {
         0.0
@@ -388,367 +1227,1550 @@ fn calculate_visibility_factor(distance: f32, min_dist: f32, max_dist: f32) -> f
     }
 }
 
-fn main() {
-    let (_stream, stream_handle) =
-        OutputStream::try_default().expect("No se pudo inicializar el stream de audio.");
-    let sink = Sink::try_new(&stream_handle).expect("No se pudo crear el sink de audio.");
+// Recursos de la escena (modelos, texturas, ruido) junto con los ajustes que se cambian
+// desde el teclado y afectan a cómo se dibuja
+struct World {
+    sphere: Vec<Vertex>,
+    moon: Vec<Vertex>,
+    // Un arreglo de vértices por material del modelo
+    ship: Vec<(String, Vec<Vertex>)>,
+    sphere_radius: f32,
+    moon_radius: f32,
+    rings: Vec<(&'static scene::RingConfig, RingBands, Vec<Vertex>)>,
+    asteroid_belt: AsteroidBelt,
+    skybox_texture: Texture,
+    skybox_cubemap: Option<Cubemap>,
+    noise: FastNoiseLite,
+    planet_textures: Vec<Texture>,
+    texture_slots: Vec<Option<usize>>,
+    show_texture: Vec<bool>,
+    skybox_exposure: f32,
+    ambient_light: f32,
+    tone_map: ToneMap,
+    seam_offset: f32,
+    parallel_bodies: bool,
+    antialias_orbits: bool,
+    // Cajas de la esfera y la luna en espacio del modelo, para descartar fuera del frustum
+    sphere_bounds: Aabb,
+    moon_bounds: Aabb,
+}
+
+impl World {
+    fn load(args: &Args, planets: &[Planet]) -> Self {
+        let obj_sphere = Obj::load("assets/models/sphere.obj").expect("Failed to load sphere.obj");
+        let mut sphere = obj_sphere.get_vertex_array();
+        apply_spherical_uvs(&mut sphere, args.seam_offset.to_radians());
+
+        let obj_moon = Obj::load("assets/models/moon.obj").expect("Failed to load moon.obj");
+        let moon = obj_moon.get_vertex_array();
+
+        let obj_ship =
+            Obj::load("assets/models/spaceship.obj").expect("Failed to load spaceship.obj");
+        let ship = obj_ship.get_vertex_arrays_by_material();
+
+        let sphere_radius = bounding_radius(&sphere);
+        let moon_radius = bounding_radius(&moon);
+        let sphere_bounds = Aabb::from_vertices(&sphere);
+        let moon_bounds = Aabb::from_vertices(&moon);
+
+        let rings = scene::RINGED_PLANETS
+            .iter()
+            .filter(|config| config.planet < planets.len())
+            .map(|config| {
+                let planet_radius = sphere_radius * planets[config.planet].scale;
+                let bands = RingBands::generate(
+                    args.ring_seed.wrapping_add(config.planet as u64),
+                    config.inner * planet_radius,
+                    config.outer * planet_radius,
+                );
+                let mesh = rings::ring_mesh(bands.inner(), bands.outer());
+                (config, bands, mesh)
+            })
+            .collect();
+
+        let skybox_texture = Texture::new("assets/textures/sky.jpg");
+        let skybox_cubemap = args.skybox_cross.as_ref().map(|path| {
+            Cubemap::from_cross(path).unwrap_or_else(|err| {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            })
+        });
+
+        let mut noise = FastNoiseLite::with_seed(args.noise_seed);
+        noise.set_frequency(Some(NOISE_FREQUENCY));
+
+        // Texturas de superficie opcionales: cada planeta guarda el índice de la suya en
+        // planet_textures, y los que tienen una empiezan mostrándola
+        let mut planet_textures = Vec::new();
+        let mut texture_slots: Vec<Option<usize>> = vec![None; planets.len()];
+        for (index, path) in &args.planet_textures {
+            let texture = if path == CHECKER_TEXTURE {
+                Texture::checkerboard(512, 16)
+            } else {
+                Texture::load(path).unwrap_or_else(|err| {
+                    eprintln!("{}", err);
+                    std::process::exit(1);
+                })
+            };
+            texture_slots[*index] = Some(planet_textures.len());
+            planet_textures.push(texture);
+        }
+        let show_texture = texture_slots.iter().map(Option::is_some).collect();
+
+        World {
+            sphere,
+            moon,
+            ship,
+            sphere_radius,
+            moon_radius,
+            rings,
+            asteroid_belt: generate_belt(args.asteroid_seed),
+            skybox_texture,
+            skybox_cubemap,
+            noise,
+            planet_textures,
+            texture_slots,
+            show_texture,
+            skybox_exposure: args.skybox_exposure,
+            ambient_light: args.ambient_light,
+            tone_map: ToneMap::Clamp,
+            seam_offset: args.seam_offset.to_radians(),
+            parallel_bodies: args.parallel_bodies,
+            antialias_orbits: args.antialias_orbits,
+            sphere_bounds,
+            moon_bounds,
+        }
+    }
+}
+
+fn generate_belt(seed: u64) -> AsteroidBelt {
+    let belt = AsteroidBelt::generate(
+        seed,
+        scene::ASTEROID_BELT_INNER,
+        scene::ASTEROID_BELT_OUTER,
+        scene::ASTEROID_COUNT,
+    );
+    println!("Semilla del cinturón de asteroides: {}", belt.seed());
+    belt
+}
+
+// Shader de cada material de la nave; los materiales sin nombre conocido van al del casco
+fn ship_shader(material: &str) -> ShaderType {
+    let material = material.to_lowercase();
+    if ["cockpit", "glass", "window"].iter().any(|part| material.contains(part)) {
+        ShaderType::GlacialTextured
+    } else {
+        ShaderType::Spaceship
+    }
+}
+
+// Dibuja la escena 3D de un cuadro: fondo, nave, cuerpos, asteroides, anillos y órbitas.
+// La comparten el bucle principal y las capturas sin ventana; los superpuestos (HUD,
+// etiquetas, posprocesado) quedan fuera
+#[allow(clippy::too_many_arguments)]
+fn render_scene(
+    framebuffer: &mut Framebuffer,
+    world: &World,
+    camera: &Camera,
+    orbits: &OrbitState,
+    time: u32,
+    view_matrix: Mat4,
+    projection_matrix: Mat4,
+    draw_ship: bool,
+    draw_skybox: bool,
+) {
+    let view_projection = projection_matrix * view_matrix;
+    let viewport_matrix =
+        create_viewport_matrix(framebuffer.width as f32, framebuffer.height as f32);
+    let distance_to_center = (camera.eye - Vec3::new(0.0, 0.0, 0.0)).magnitude();
+    let visibility_factor = calculate_visibility_factor(distance_to_center, 30.0, 70.0);
+
+    // Renderizar el skybox
+    let base_uniforms = Uniforms {
+        model_matrix: Mat4::identity(),
+        view_matrix,
+        projection_matrix,
+        viewport_matrix,
+        time,
+        noise: &world.noise,
+        camera_eye: camera.eye,
+        center: Vec3::new(0.0, 0.0, 0.0),
+        occluder: None,
+        emissive: false,
+        textures: &world.planet_textures,
+        ambient: world.ambient_light,
+        tone_map: world.tone_map,
+    };
+
+    if draw_skybox {
+        render_skybox(
+            framebuffer,
+            camera,
+            &world.skybox_texture,
+            world.skybox_cubemap.as_ref(),
+            world.seam_offset,
+            world.skybox_exposure,
+            &base_uniforms,
+        );
+    }
 
-    let file =
-        File::open("assets/audio/ewtrtw.wav").expect("No se pudo abrir el archivo de música.");
-    let source =
-        Decoder::new(BufReader::new(file)).expect("No se pudo decodificar el archivo de música.");
+    if draw_ship {
+        let ship_position = camera.eye + (camera.center - camera.eye).normalize() * SHIP_OFFSET;
+        let ship_rotation_angle = std::f32::consts::PI;
 
-    sink.append(source.repeat_infinite());
+        let ship_uniforms = Uniforms {
+            model_matrix: create_model_matrix(ship_position, 0.1, ship_rotation_angle),
+            view_matrix,
+            projection_matrix,
+            viewport_matrix,
+            time,
+            noise: &world.noise,
+            camera_eye: camera.eye,
+            center: ship_position,
+            occluder: None,
+            emissive: false,
+            textures: &world.planet_textures,
+            ambient: world.ambient_light,
+            tone_map: world.tone_map,
+        };
+        for (material, vertices) in &world.ship {
+            render(
+                framebuffer,
+                &ship_uniforms,
+                vertices,
+                &ship_shader(material),
+                CULL_CLOCKWISE,
+            );
+        }
+    }
 
-    sink.set_volume(0.2);
+    let sun_rotation_speed = 0.0001;
+    let sun_rotation = time as f32 * sun_rotation_speed;
+
+    // Los cuerpos se acumulan en una lista y se dibujan juntos (en paralelo si se pidió);
+    // las órbitas van después para que su desvanecimiento vea todos los cuerpos
+    let mut bodies = Vec::new();
+    let mut orbit_draws = Vec::new();
+    // Halos de atmósfera: translúcidos, se mezclan cuando ya está dibujado todo lo opaco
+    let mut atmospheres = Vec::new();
+
+    // Renderizado del sol
+    let sun_scale = scene::SUN_SCALE;
+    let sun_uniforms = Uniforms {
+        model_matrix: create_model_matrix(Vec3::new(0.0, 0.0, 0.0), sun_scale, sun_rotation),
+        view_matrix,
+        projection_matrix,
+        viewport_matrix,
+        time,
+        noise: &world.noise,
+        camera_eye: camera.eye,
+        center: Vec3::new(0.0, 0.0, 0.0),
+        occluder: None,
+        emissive: scene::SUN_EMISSIVE,
+        textures: &world.planet_textures,
+        ambient: world.ambient_light,
+        tone_map: world.tone_map,
+    };
+    bodies.push(BodyDraw {
+        uniforms: sun_uniforms,
+        vertices: &world.sphere,
+        shader: ShaderType::Solar,
+        radius: world.sphere_radius * sun_scale,
+    });
+
+    for (i, planet) in orbits.planets().iter().enumerate() {
+        let distance_to_camera = (camera.eye - Vec3::new(0.0, 0.0, 0.0)).magnitude();
+
+        let planet_position = orbits.planet_position(i, time);
+
+        let planet_scale = planet.scale;
+        let planet_rotation = orbits.planet_rotation(i, time);
+
+        // La luna y su planeta se pueden eclipsar entre sí
+        let planet_radius = world.sphere_radius * planet_scale;
+        let moon_sphere = planet.has_moon.then(|| {
+            Sphere::new(
+                scene::moon_position(&planet_position, time),
+                world.moon_radius * scene::MOON_SCALE,
+            )
+        });
+
+        // Verificar si la caja del planeta toca el frustum
+        let planet_model =
+            create_tilted_model_matrix(planet_position, planet_scale, planet.tilt, planet_rotation);
+        if world.sphere_bounds.transformed(&planet_model).is_in_frustum(&view_projection) {
+            // Renderizar planeta
+            let planet_uniforms = Uniforms {
+                model_matrix: planet_model,
+                view_matrix,
+                projection_matrix,
+                viewport_matrix,
+                time,
+                noise: &world.noise,
+                camera_eye: camera.eye,
+                center: planet_position,
+                occluder: moon_sphere,
+                emissive: planet.emissive,
+                textures: &world.planet_textures,
+                ambient: world.ambient_light,
+                tone_map: world.tone_map,
+            };
+
+            bodies.push(BodyDraw {
+                uniforms: planet_uniforms,
+                vertices: &world.sphere,
+                shader: match world.texture_slots[i] {
+                    Some(slot) if world.show_texture[i] => ShaderType::Textured(slot),
+                    _ => planet.shader.clone(),
+                },
+                radius: planet_radius,
+            });
+
+            if let Some(tint) = planet.atmosphere {
+                atmospheres.push((
+                    Uniforms {
+                        model_matrix: create_tilted_model_matrix(
+                            planet_position,
+                            planet_scale * scene::ATMOSPHERE_SCALE,
+                            planet.tilt,
+                            planet_rotation,
+                        ),
+                        view_matrix,
+                        projection_matrix,
+                        viewport_matrix,
+                        time,
+                        noise: &world.noise,
+                        camera_eye: camera.eye,
+                        center: planet_position,
+                        occluder: None,
+                        emissive: false,
+                        textures: &world.planet_textures,
+                        ambient: world.ambient_light,
+                        tone_map: world.tone_map,
+                    },
+                    ShaderType::Atmosphere(tint),
+                ));
+            }
 
+            // Renderizar órbita solo si la cámara está lo suficientemente lejos, con un
+            // desvanecimiento gradual dentro de la banda de visibilidad
+            let orbit_distance = distance_to_camera - planet.max_distance();
+            let orbit_opacity = ((orbit_distance - ORBIT_VISIBILITY_FADE_START)
+                / (ORBIT_VISIBILITY_FADE_END - ORBIT_VISIBILITY_FADE_START))
+                .clamp(0.0, 1.0);
+            if orbit_opacity > 0.0 {
+                let orbit_scale = 0.1;
+                if is_in_frustum(
+                    &Vec3::new(0.0, 0.0, 0.0),
+                    planet.max_distance() + orbit_scale,
+                    &view_matrix,
+                    &projection_matrix,
+                ) {
+                    orbit_draws.push((planet, orbit_opacity));
+                }
+            }
 
-    sink.play();
+            // Renderizar la luna junto a su planeta
+            if planet.has_moon {
+                let moon_position = scene::moon_position(&planet_position, time);
+
+                let moon_rotation = scene::moon_rotation(time);
+
+                let moon_scale = scene::MOON_SCALE;
+                let moon_model = create_model_matrix(moon_position, moon_scale, moon_rotation);
+                if world.moon_bounds.transformed(&moon_model).is_in_frustum(&view_projection) {
+                    let moon_uniforms = Uniforms {
+                        model_matrix: moon_model,
+                        view_matrix,
+                        projection_matrix,
+                        viewport_matrix,
+                        time,
+                        noise: &world.noise,
+                        camera_eye: camera.eye,
+                        center: moon_position,
+                        occluder: Some(Sphere::new(planet_position, planet_radius)),
+                        emissive: false,
+                        textures: &world.planet_textures,
+                        ambient: world.ambient_light,
+                        tone_map: world.tone_map,
+                    };
+
+                    bodies.push(BodyDraw {
+                        uniforms: moon_uniforms,
+                        vertices: &world.moon,
+                        shader: ShaderType::Moon,
+                        radius: world.moon_radius * moon_scale,
+                    });
+                }
+            }
+        }
+    }
+
+    // Los tiles no llevan el contador de sobredibujado ni sirven para el relleno de solo
+    // profundidad del modo de alambre, y al componerlos por profundidad se perderían los
+    // bordes parciales del antialiasing, así que esos modos dibujan en serie
+    if world.parallel_bodies
+        && framebuffer.overdraw.is_none()
+        && framebuffer.render_mode == RenderMode::Filled
+        && !framebuffer.edge_antialiasing
+    {
+        render_bodies_parallel(framebuffer, &bodies);
+    } else {
+        for body in &bodies {
+            render(
+                framebuffer,
+                &body.uniforms,
+                body.vertices,
+                &body.shader,
+                CULL_CLOCKWISE,
+            );
+        }
+    }
 
-    let window_width = 1000;
-    let window_height = 800;
-    let framebuffer_width = 1000;
-    let framebuffer_height = 800;
+    render_asteroid_belt(framebuffer, world, &base_uniforms, &view_projection);
+
+    let sun_position = Vec3::new(0.0, 0.0, 0.0);
+    let comet_position = comet::comet_position(time);
+    comet::render_comet(framebuffer, &comet_position, &sun_position, &base_uniforms);
+
+    framebuffer.set_blend_mode(BlendMode::AlphaBlend);
+    for (uniforms, shader) in &atmospheres {
+        render(framebuffer, uniforms, &world.sphere, shader, CULL_CLOCKWISE);
+    }
+    framebuffer.set_blend_mode(BlendMode::Opaque);
+
+    // Anillos semitransparentes, después de los cuerpos para mezclarse sobre ellos
+    for (config, bands, mesh) in &world.rings {
+        let planet_position = orbits.planet_position(config.planet, time);
+        if !is_in_frustum(
+            &planet_position,
+            bands.outer(),
+            &view_matrix,
+            &projection_matrix,
+        ) {
+            continue;
+        }
+        let ring_uniforms = Uniforms {
+            model_matrix: Mat4::new_translation(&planet_position)
+                * Mat4::from_axis_angle(&Vec3::x_axis(), config.tilt),
+            view_matrix,
+            projection_matrix,
+            viewport_matrix,
+            time,
+            noise: &world.noise,
+            camera_eye: camera.eye,
+            center: planet_position,
+            occluder: None,
+            emissive: false,
+            textures: &world.planet_textures,
+            ambient: world.ambient_light,
+            tone_map: world.tone_map,
+        };
+        let planet = Sphere::new(
+            planet_position,
+            world.sphere_radius * orbits.planets()[config.planet].scale,
+        );
+        rings::render_rings(framebuffer, &ring_uniforms, mesh, bands, &planet);
+    }
+
+    for &(planet, orbit_opacity) in &orbit_draws {
+        render_orbit_lines(
+            framebuffer,
+            planet,
+            Color::new(128, 128, 128, 255),
+            150,
+            &base_uniforms,
+            visibility_factor,
+            orbit_opacity,
+            world.antialias_orbits,
+        );
+    }
+}
+
+// Dibuja un único cuadro sin abrir ventana ni audio y lo guarda como imagen. El tiempo
+// se pasa fijo, así que la misma llamada produce siempre la misma imagen
+fn render_frame_to_file(
+    path: &str,
+    time: u32,
+    camera: &Camera,
+    world: &World,
+    orbits: &OrbitState,
+    args: &Args,
+) -> Result<(), String> {
+    let (width, height, ssaa, projection) = (args.width, args.height, args.ssaa, args.projection);
+    let mut scene_framebuffer = Framebuffer::new(width * ssaa, height * ssaa);
+    scene_framebuffer.set_background_color(0x000000);
+    scene_framebuffer.edge_antialiasing = args.edge_aa;
+    scene_framebuffer.clear();
+
+    let projection_matrix = create_perspective_matrix(
+        width as f32,
+        height as f32,
+        projection.fov_deg,
+        projection.near,
+        projection.far,
+    );
+    let view_matrix = camera.current_view_matrix();
+    render_scene(
+        &mut scene_framebuffer,
+        world,
+        camera,
+        orbits,
+        time,
+        view_matrix,
+        projection_matrix,
+        true,
+        true,
+    );
+    let mut framebuffer = if ssaa > 1 {
+        let mut framebuffer = Framebuffer::new(width, height);
+        framebuffer.downsample_from(&scene_framebuffer, ssaa);
+        framebuffer
+    } else {
+        scene_framebuffer
+    };
+    framebuffer.apply_bloom(BLOOM_THRESHOLD, BLOOM_RADIUS, BLOOM_INTENSITY);
+
+    framebuffer
+        .save_image(path)
+        .map_err(|err| format!("No se pudo guardar la captura {}: {}", path, err))
+}
+
+fn main() {
+    let args = match Args::parse() {
+        Ok(args) => args,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+
+    // Sistema solar: el archivo de --system o system.toml; si no existe, el de por defecto
+    let system_path = args.system.as_deref().unwrap_or(system::DEFAULT_SYSTEM_PATH);
+    if args.system.is_some() && !std::path::Path::new(system_path).exists() {
+        eprintln!("Aviso: no existe {}, se usa el sistema por defecto", system_path);
+    }
+    // El sistema se carga una sola vez y lo comparten la simulación, el HUD y la exportación
+    let mut orbits = match system::load_system(system_path) {
+        Ok(planets) => OrbitState::new(planets),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    };
+    let planet_count = orbits.planets().len();
+    let missing_planet = args.planet_textures.iter().find(|(index, _)| *index >= planet_count);
+    if let Some((index, _)) = missing_planet {
+        eprintln!("--planet-texture: no hay planeta {}", index + 1);
+        std::process::exit(1);
+    }
+
+    if let Some(path) = &args.export_orbits {
+        let (start, end, step) = (args.time_start, args.time_end, args.time_step);
+        match export::export_orbit_timeline(path, &orbits, start, end, step) {
+            Ok(rows) => println!("Se exportaron {} posiciones a {}", rows, path),
+            Err(err) => {
+                eprintln!("No se pudo exportar la línea de tiempo: {}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    let mut world = World::load(&args, orbits.planets());
+    let mut camera = Camera::new(
+        Vec3::new(0.0, 50.0, 150.0),
+        Vec3::new(0.0, 0.0, 0.0),
+        Vec3::new(0.0, 1.0, 0.0),
+    );
+    // Encuadre inicial, al que vuelve la tecla Home
+    let home_view = (camera.eye, camera.center, camera.up);
+
+    if let Some(path) = &args.screenshot {
+        let saved = render_frame_to_file(path, args.time, &camera, &world, &orbits, &args);
+        match saved {
+            Ok(()) => println!("Captura guardada en {}", path),
+            Err(err) => {
+                eprintln!("{}", err);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    // Sin salida de audio o sin pistas la simulación sigue en silencio. El stream tiene que
+    // vivir mientras suene la música
+    let audio_stream = OutputStream::try_default()
+        .map_err(|err| format!("No se pudo inicializar el stream de audio: {}", err));
+    let music = audio_stream
+        .as_ref()
+        .map_err(Clone::clone)
+        .and_then(|(_, stream_handle)| MusicPlayer::new(stream_handle.clone(), &args.music));
+    let mut music = match music {
+        Ok(music) => Some(music),
+        Err(err) => {
+            eprintln!("Aviso: {} (se continúa sin música)", err);
+            None
+        }
+    };
+    // El golpe al chocar es opcional: si no se puede cargar, los choques siguen siendo mudos
+    let collision_sound = audio_stream.as_ref().ok().and_then(|(_, stream_handle)| {
+        SoundEffect::new(stream_handle, &args.collision_sound).ok()
+    });
+
+    let mut window_width = args.width;
+    let mut window_height = args.height;
     let frame_delay = Duration::from_millis(16);
 
-    let mut framebuffer = Framebuffer::new(framebuffer_width, framebuffer_height);
+    let mut framebuffer = Framebuffer::new(window_width, window_height);
     let mut window = Window::new(
         "Sistema Solar",
         window_width,
         window_height,
-        WindowOptions::default(),
+        WindowOptions {
+            // minifb no tiene pantalla completa real: se usa una ventana sin bordes y
+            // siempre encima, del tamaño pedido (normalmente la resolución del monitor)
+            borderless: args.fullscreen,
+            title: !args.fullscreen,
+            topmost: args.fullscreen,
+            resize: true,
+            ..WindowOptions::default()
+        },
     )
     .unwrap();
 
     framebuffer.set_background_color(0x000000);
+    framebuffer.scissor = args.render_region;
+    framebuffer.edge_antialiasing = args.edge_aa;
+    // Supermuestreo: la escena 3D se dibuja en un framebuffer --ssaa veces mayor en cada eje
+    // y se reduce al de la ventana. Gizmos, etiquetas, posprocesado y HUD van encima a la
+    // resolución normal
+    let ssaa = args.ssaa;
+    let mut supersampled = (ssaa > 1).then(|| {
+        let mut supersampled = Framebuffer::new(window_width * ssaa, window_height * ssaa);
+        supersampled.set_background_color(0x000000);
+        supersampled.scissor = args.render_region.map(|region| region.scaled(ssaa));
+        supersampled
+    });
 
-    let obj_sphere = Obj::load("assets/models/sphere.obj").expect("Failed to load sphere.obj");
-    let vertex_arrays_sphere = obj_sphere.get_vertex_array();
-
-    let obj_moon = Obj::load("assets/models/moon.obj").expect("Failed to load moon.obj");
-    let vertex_arrays_moon = obj_moon.get_vertex_array();
-
-    let obj_ship = Obj::load("assets/models/spaceship.obj").expect("Failed to load spaceship.obj");
-    let vertex_arrays_ship = obj_ship.get_vertex_array();
-
-    let mut camera = Camera::new(
-        Vec3::new(0.0, 50.0, 150.0),
-        Vec3::new(0.0, 0.0, 0.0),
-        Vec3::new(0.0, 1.0, 0.0),
+    let projection = args.projection;
+    let mut projection_matrix = create_perspective_matrix(
+        window_width as f32,
+        window_height as f32,
+        projection.fov_deg,
+        projection.near,
+        projection.far,
     );
+    let mut viewport_matrix = create_viewport_matrix(window_width as f32, window_height as f32);
 
-    let projection_matrix = create_perspective_matrix(window_width as f32, window_height as f32);
-    let viewport_matrix =
-        create_viewport_matrix(framebuffer_width as f32, framebuffer_height as f32);
-
-    let orbital_radii = vec![15.0, 25.0, 35.0, 45.0, 55.0, 65.0];
-    let orbital_speeds = vec![0.04, 0.017, 0.014, 0.03, 0.010, 0.009];
-    let shaders = vec![
-        ShaderType::RockyPlanet,
-        ShaderType::RockyPlanetVariant,
-        ShaderType::GasGiant,
-        ShaderType::ColdGasGiant,
-        ShaderType::AlienPlanet,
-        ShaderType::GlacialTextured,
-    ];
     // Variables para controlar la cámara
     let camera_speed = 1.0;
     let rotation_speed = 0.05;
     let zoom_speed = 2.0;
     let vertical_speed = 1.0;
 
-    let skybox_texture = Texture::new("assets/textures/sky.jpg");
-
     let mut time = 0;
-    let planet_scales = vec![2.5, 3.0, 4.0, 5.0, 4.5, 5.0];
-    let mut planet_positions = vec![Vec3::zeros(); orbital_radii.len()];
+    let mut planet_positions = vec![Vec3::zeros(); planet_count];
+    let mut selected_body: Option<Body> = None;
+    let mut follow_selected = false;
+    let mut warp: Option<(usize, CameraAnimation)> = None;
+    let mut cursor_hidden = false;
+    let mut scroll_zoom = 0.0;
+    let mut was_colliding = false;
+    let mut paused = false;
+    let mut camera_mode = CameraMode::Orbit;
+    let orbital_speed_step = 0.002;
+    let planet_keys = [Key::Key1, Key::Key2, Key::Key3, Key::Key4, Key::Key5, Key::Key6];
+    let mut frozen_view_projection: Option<Mat4> = None;
+    let mut show_cockpit = false;
+    let mut motion_blur_enabled = false;
+    let mut god_rays_enabled = false;
+    let mut bloom_enabled = true;
+    let mut show_depth = false;
+    // Modo estela: copia de la escena del cuadro anterior (sin HUD ni posprocesado), que se
+    // atenúa en lugar de borrarse
+    let mut trail: Option<Vec<u32>> = None;
+    let mut label_mode = LabelMode::Hidden;
+    let mut noclip = false;
+    let mut show_velocity_hud = false;
+    let mut show_inspector = false;
+    let mut show_light_gizmos = false;
+    let mut show_frame_stats = false;
+    let mut show_minimap = false;
+    let mut frame_stats = hud::FrameStats::new();
+    let mut motion_blur = MotionBlur::new();
+    let mut previous_eye = camera.eye;
+    let mut previous_direction = (camera.center - camera.eye).normalize();
+
+    let mut idle_frames: u32 = 0;
+    let mut last_frame = Instant::now();
+    let mut input = Input::new();
+    if let Some(path) = &args.record {
+        if let Err(err) = input.start_recording(path) {
+            eprintln!("No se pudo crear la grabación {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &args.replay {
+        if let Err(err) = input.start_replay(path) {
+            eprintln!("No se pudo cargar la grabación {}: {}", path, err);
+            std::process::exit(1);
+        }
+    }
+    let mut audio_paused_in_background = false;
 
     while window.is_open() {
         if window.is_key_down(Key::Escape) {
             break;
         }
 
+        // Ventana sin foco o minimizada: no se renderiza, pero se siguen procesando sus
+        // eventos para poder restaurarla
+        if !window.is_active() {
+            if let Some(music) = &music {
+                if args.pause_audio_in_background && !music.is_paused() {
+                    music.pause();
+                    audio_paused_in_background = true;
+                }
+            }
+            window.update();
+            std::thread::sleep(BACKGROUND_FRAME_DELAY);
+            continue;
+        }
+        if let (true, Some(music)) = (audio_paused_in_background, &music) {
+            music.play();
+            audio_paused_in_background = false;
+        }
+        if let Some(music) = &mut music {
+            music.set_sun_distance((camera.eye - Vec3::new(0.0, 0.0, 0.0)).magnitude());
+            music.update();
+        }
+
+        // Ventana redimensionada: el framebuffer y las matrices que dependen del tamaño se
+        // rehacen antes de dibujar, porque update_with_buffer recibe un buffer del tamaño de
+        // la ventana. Los efectos que guardan el cuadro anterior se reinician solos al ver que
+        // no coincide
+        let (width, height) = window.get_size();
+        if (width, height) != (window_width, window_height) && width > 0 && height > 0 {
+            window_width = width;
+            window_height = height;
+            framebuffer.resize(width, height);
+            if let Some(supersampled) = &mut supersampled {
+                supersampled.resize(width * ssaa, height * ssaa);
+            }
+            projection_matrix = create_perspective_matrix(
+                width as f32,
+                height as f32,
+                projection.fov_deg,
+                projection.near,
+                projection.far,
+            );
+            viewport_matrix = create_viewport_matrix(width as f32, height as f32);
+        }
+
+        if let Err(err) = input.update(&window) {
+            eprintln!("Error al escribir la grabación: {}", err);
+            input.stop_recording().ok();
+        }
+
+        // Modo de reposo: tras un rato sin entrada la cámara orbita lentamente. En pausa no
+        // gira, para que el cuadro quede quieto y se pueda bajar la frecuencia
+        if input.has_activity() {
+            idle_frames = 0;
+        } else if idle_frames < IDLE_FRAMES {
+            idle_frames += 1;
+        } else if !paused {
+            camera.orbit(IDLE_ORBIT_SPEED, 0.0);
+        }
+
+        // Selección de planeta (con vuelo hacia él) y ajuste de su velocidad orbital. La
+        // cámara acaba entre el planeta y el sol, algo por encima de la órbita. Un sistema
+        // con menos de seis planetas deja sin uso las teclas sobrantes
+        for (i, &key) in planet_keys.iter().take(planet_count).enumerate() {
+            if input.is_key_pressed(key, KeyRepeat::No) {
+                selected_body = Some(Body::Planet(i));
+                println!("Cuerpo seleccionado: {}", orbits.planets()[i].name);
+
+                let sunward = -orbits.planet_position(i, time).normalize();
+                let direction = (sunward + Vec3::new(0.0, WARP_ELEVATION, 0.0)).normalize();
+                let radius = world.sphere_radius * orbits.planets()[i].scale;
+                let distance = SHIP_OFFSET + radius * WARP_DISTANCE_RADII;
+                warp = Some((i, CameraAnimation::new(&camera, direction * distance)));
+            }
+        }
+        if input.is_key_pressed(Key::Space, KeyRepeat::No) {
+            // La música se detiene con la simulación y sigue donde se quedó al reanudar
+            paused = !paused;
+            match &music {
+                Some(music) if paused => music.pause(),
+                Some(music) => music.play(),
+                None => {}
+            }
+            println!("{}", if paused { "Simulación en pausa" } else { "Simulación reanudada" });
+        }
+        // Clic izquierdo: selecciona el cuerpo bajo el cursor. Un índice sin cuerpo no
+        // cambia la selección
+        if let Some((x, y)) = input.click() {
+            let ndc_x = 2.0 * x / window_width as f32 - 1.0;
+            let ndc_y = 1.0 - 2.0 * y / window_height as f32;
+            let objects = body_triangles(&world, &orbits, time);
+            let view_matrix = camera.view_matrix();
+            let picked = pick(
+                &view_matrix,
+                &projection_matrix,
+                ndc_x,
+                ndc_y,
+                &objects,
+                true,
+            );
+            let body = picked.and_then(|index| scene::bodies(orbits.planets()).nth(index));
+            if let Some(body) = body {
+                selected_body = Some(body);
+                println!("Cuerpo seleccionado: {}", body.name(orbits.planets()));
+            }
+        }
+        if input.is_key_pressed(Key::Tab, KeyRepeat::No) {
+            let body = selected_body.map_or(Body::Sun, |body| body.next(orbits.planets()));
+            selected_body = Some(body);
+            println!("Cuerpo seleccionado: {}", body.name(orbits.planets()));
+        }
+        if input.is_key_pressed(Key::Y, KeyRepeat::No) {
+            match selected_body {
+                Some(Body::Planet(i)) if world.texture_slots[i].is_some() => {
+                    world.show_texture[i] = !world.show_texture[i];
+                    let mode = if world.show_texture[i] {
+                        "textura"
+                    } else {
+                        "procedural"
+                    };
+                    println!("{}: {}", orbits.planets()[i].name, mode);
+                }
+                Some(body) => {
+                    println!("{} no tiene textura asignada", body.name(orbits.planets()))
+                }
+                None => {}
+            }
+        }
+        if input.is_key_pressed(Key::U, KeyRepeat::No) {
+            follow_selected = !follow_selected;
+        }
+        if let Some(Body::Planet(i)) = selected_body {
+            let mut speed_delta = 0.0;
+            if input.is_key_pressed(Key::RightBracket, KeyRepeat::Yes) {
+                speed_delta += orbital_speed_step;
+            }
+            if input.is_key_pressed(Key::LeftBracket, KeyRepeat::Yes) {
+                speed_delta -= orbital_speed_step;
+            }
+            if speed_delta != 0.0 {
+                let speed = orbits.set_speed(i, orbits.speed(i) + speed_delta, time);
+                println!("Velocidad orbital de {}: {:.3}", orbits.planets()[i].name, speed);
+            }
+        }
+
         // Actualizar las posiciones de los planetas
-        for (i, &radius) in orbital_radii.iter().enumerate() {
-            let planet_x = radius * (time as f32 * orbital_speeds[i]).cos();
-            let planet_z = radius * (time as f32 * orbital_speeds[i]).sin();
-            planet_positions[i] = Vec3::new(planet_x, 0.0, planet_z);
+        for (i, planet_position) in planet_positions.iter_mut().enumerate() {
+            *planet_position = orbits.planet_position(i, time);
+        }
+
+        // Seguimiento: la cámara se desplaza con el cuerpo seleccionado y lo mantiene centrado.
+        // Se usa su posición en el cuadro que se va a dibujar (el tiempo avanza antes de renderizar).
+        let next_time = if paused { time } else { time + 1 };
+        if let (true, Some(body)) = (follow_selected, selected_body) {
+            camera.move_center(body.position(&orbits, next_time) - camera.center);
         }
 
-        // Movimiento en el plano horizontal (XZ)
-        let mut movement = Vec3::new(0.0, 0.0, 0.0);
-        if window.is_key_down(Key::W) {
+        let sun_radius = world.sphere_radius * scene::SUN_SCALE;
+
+        // Choque en este cuadro con cualquiera de los movimientos, para sonar solo al empezar
+        let mut colliding = false;
+
+        // Con Shift izquierdo la cámara se mueve y hace zoom más rápido. Las colisiones se
+        // comprueban con el desplazamiento ya multiplicado
+        let boost = if input.is_key_down(Key::LeftShift) {
+            BOOST_FACTOR
+        } else {
+            1.0
+        };
+
+        // Movimiento en el plano horizontal (XZ). El stick izquierdo del mando se suma a las
+        // teclas en proporción a su inclinación
+        let pad = input.gamepad();
+        let mut movement = Vec3::new(pad.movement.0, 0.0, -pad.movement.1) * camera_speed;
+        if input.is_key_down(Key::W) {
             movement.z -= camera_speed;
         }
-        if window.is_key_down(Key::S) {
+        if input.is_key_down(Key::S) {
             movement.z += camera_speed;
         }
-        if window.is_key_down(Key::A) {
+        if input.is_key_down(Key::A) {
             movement.x -= camera_speed;
         }
-        if window.is_key_down(Key::D) {
+        if input.is_key_down(Key::D) {
             movement.x += camera_speed;
         }
+        // En vuelo libre W/S avanzan hacia donde mira la cámara y A/D se desplazan de lado
+        if camera_mode == CameraMode::Free {
+            movement = camera.fly_offset(-movement.z, movement.x, 0.0);
+        }
+        movement *= boost;
+
+        // Normal de contacto con el sol o un planeta en `position` (sin choques con noclip)
+        let moon_parent = scene::moon_parent(orbits.planets());
+        let moon_position = scene::moon_position(&planet_positions[moon_parent], time);
+        let body_contact = |position: Vec3| {
+            if noclip {
+                return None;
+            }
+            let sun = Vec3::new(0.0, 0.0, 0.0);
+            let planet_contact = || {
+                planet_positions
+                    .iter()
+                    .zip(orbits.planets())
+                    .find_map(|(pos, planet)| {
+                        check_collision(
+                            &position,
+                            pos,
+                            world.sphere_radius * planet.scale,
+                            SHIP_SIZE,
+                            PLANET_COLLISION_MARGIN,
+                        )
+                    })
+            };
+            check_collision(&position, &sun, sun_radius, SHIP_SIZE, SUN_COLLISION_MARGIN)
+                .or_else(planet_contact)
+        };
 
+        // El sol y los planetas se comprueban con la nave, delante de la cámara, y la luna
+        // con la propia cámara
         if movement.magnitude() > 0.0 {
-            let ship_offset = 15.0;
-            let future_position = camera.eye + movement;
-            let future_ship_position =
-                future_position + (camera.center - future_position).normalize() * ship_offset;
-
-            let mut collision = false;
-
-            // Verificar colisión con el sol primero
-            if check_collision(&future_ship_position, &Vec3::new(0.0, 0.0, 0.0), 4.0) {
-                collision = true;
-            }
-
-            // Verificar colisiones con cada planeta
-            if !collision {
-                for (i, planet_pos) in planet_positions.iter().enumerate() {
-                    let planet_scale = planet_scales[i];
-                    if check_collision(&future_ship_position, planet_pos, planet_scale) {
-                        collision = true;
-                        break;
-                    }
-                }
+            let forward = (camera.center - camera.eye).normalize();
+            let (allowed, collision) = slide_movement(movement, |offset| {
+                let future_position = camera.eye + offset;
+                body_contact(future_position + forward * SHIP_OFFSET).or_else(|| {
+                    check_collision(
+                        &future_position,
+                        &moon_position,
+                        world.moon_radius * scene::MOON_SCALE,
+                        MOON_SHIP_SIZE,
+                        MOON_COLLISION_MARGIN,
+                    )
+                    .filter(|_| !noclip)
+                })
+            });
+            if allowed != Vec3::zeros() {
+                camera.move_center(allowed);
             }
+            colliding |= collision;
+        }
 
-            // Verificar colisión con la luna
-            if !collision && !planet_positions.is_empty() {
-                let orbit_radius_moon = 2.0;
-                let orbit_speed_moon = 0.01;
-                let moon_x = planet_positions[0].x
-                    + orbit_radius_moon * (time as f32 * orbit_speed_moon).cos();
-                let moon_z = planet_positions[0].z
-                    + orbit_radius_moon * (time as f32 * orbit_speed_moon).sin();
-                let moon_position = Vec3::new(moon_x, 0.0, moon_z);
-
-                if check_collision(&future_position, &moon_position, 0.5) {
-                    collision = true;
-                }
-            }
-            if !collision {
-                camera.move_center(movement);
+        // Movimiento vertical con colisiones (R/F o los botones inferior y derecho del mando)
+        let keys_vertical = match (input.is_key_down(Key::R), input.is_key_down(Key::F)) {
+            (true, false) => 1.0,
+            (false, true) => -1.0,
+            _ => 0.0,
+        };
+        let vertical = (keys_vertical + pad.vertical).clamp(-1.0, 1.0) * vertical_speed * boost;
+        if vertical != 0.0 {
+            let vertical_movement = match camera_mode {
+                CameraMode::Orbit => Vec3::new(0.0, vertical, 0.0),
+                CameraMode::Free => camera.fly_offset(0.0, 0.0, vertical),
+            };
+            let (allowed, collision) = slide_movement(vertical_movement, |offset| {
+                body_contact(camera.eye + offset)
+            });
+            if allowed != Vec3::zeros() {
+                camera.move_center(allowed);
             }
+            colliding |= collision;
         }
 
-        // Movimiento vertical con colisiones
-        if window.is_key_down(Key::R) {
-            let up_movement = Vec3::new(0.0, vertical_speed, 0.0);
-            let future_position = camera.eye + up_movement;
-            let collision = check_collision(&future_position, &Vec3::new(0.0, 0.0, 0.0), 4.0)
-                || planet_positions
-                    .iter()
-                    .enumerate()
-                    .any(|(i, pos)| check_collision(&future_position, pos, planet_scales[i]));
+        if let (true, false, Some(sound)) = (colliding, was_colliding, &collision_sound) {
+            sound.play();
+        }
+        was_colliding = colliding;
 
-            if !collision {
-                camera.move_vertical(vertical_speed);
+        // Rotación de la cámara: en órbita gira alrededor del centro, en vuelo libre
+        // gira la vista sin mover el ojo
+        if input.is_key_pressed(Key::C, KeyRepeat::No) {
+            camera_mode = camera_mode.toggle();
+            println!("Cámara: {:?}", camera_mode);
+        }
+        // Home: vuelve a la vista general del inicio en modo órbita, sin seguimiento ni vuelo
+        // en curso
+        if input.is_key_pressed(Key::Home, KeyRepeat::No) {
+            let (eye, center, up) = home_view;
+            camera.reset(eye, center, up);
+            camera_mode = CameraMode::Orbit;
+            follow_selected = false;
+            warp = None;
+            scroll_zoom = 0.0;
+            println!("Cámara reiniciada");
+        }
+        // El stick derecho gira como las flechas; hacia arriba equivale a la flecha arriba
+        let mut yaw = pad.look.0 * rotation_speed;
+        let mut pitch = -pad.look.1 * rotation_speed;
+        if input.is_key_down(Key::Left) {
+            yaw -= rotation_speed;
+        }
+        if input.is_key_down(Key::Right) {
+            yaw += rotation_speed;
+        }
+        if input.is_key_down(Key::Up) {
+            pitch -= rotation_speed;
+        }
+        if input.is_key_down(Key::Down) {
+            pitch += rotation_speed;
+        }
+        // Arrastrar con el botón derecho gira la cámara igual que las flechas
+        let (drag_x, drag_y) = input.mouse_drag();
+        yaw += drag_x * MOUSE_LOOK_SENSITIVITY;
+        pitch += drag_y * MOUSE_LOOK_SENSITIVITY;
+        if input.is_dragging() != cursor_hidden {
+            cursor_hidden = input.is_dragging();
+            window.set_cursor_visibility(!cursor_hidden);
+        }
+        if yaw != 0.0 || pitch != 0.0 {
+            match camera_mode {
+                CameraMode::Orbit => camera.orbit(yaw, pitch),
+                CameraMode::Free => camera.yaw_pitch(yaw, -pitch),
             }
         }
-        if window.is_key_down(Key::F) {
-            let down_movement = Vec3::new(0.0, -vertical_speed, 0.0);
-            let future_position = camera.eye + down_movement;
-            let collision = check_collision(&future_position, &Vec3::new(0.0, 0.0, 0.0), 4.0)
-                || planet_positions
-                    .iter()
-                    .enumerate()
-                    .any(|(i, pos)| check_collision(&future_position, pos, planet_scales[i]));
 
-            if !collision {
-                camera.move_vertical(-vertical_speed);
+        // Zoom: las teclas avanzan a paso fijo y la rueda acumula un desplazamiento que se
+        // reparte entre varios cuadros, para que la rueda y el trackpad no den saltos. El
+        // gatillo derecho del mando acerca y el izquierdo aleja
+        let mut zoom = -pad.zoom * zoom_speed * boost;
+        if input.is_key_down(Key::Q) {
+            zoom -= zoom_speed * boost;
+        }
+        if input.is_key_down(Key::E) {
+            zoom += zoom_speed * boost;
+        }
+        scroll_zoom += input.scroll() * zoom_speed * boost;
+        let scroll_step = scroll_zoom * SCROLL_ZOOM_EASING;
+        scroll_zoom -= scroll_step;
+        if scroll_zoom.abs() < SCROLL_ZOOM_EPSILON {
+            scroll_zoom = 0.0;
+        }
+        zoom += scroll_step;
+        if zoom != 0.0 {
+            // Al acercarse no se entra en ningún cuerpo, salvo con noclip o si ya se estaba
+            // dentro
+            let surface_distance = |eye: &Vec3| {
+                nearest_surface_distance(
+                    eye,
+                    orbits.planets(),
+                    &planet_positions,
+                    &moon_position,
+                    world.sphere_radius,
+                    world.moon_radius,
+                )
+            };
+            // Se prueba en varios puntos del recorrido, como los demás movimientos
+            let zoom_step = (camera.center - camera.eye).normalize() * zoom;
+            let clear_path = (1..=COLLISION_SUBSTEPS).all(|step| {
+                let t = step as f32 / COLLISION_SUBSTEPS as f32;
+                surface_distance(&(camera.eye + zoom_step * t)) > 0.0
+            });
+            if noclip || zoom < 0.0 || clear_path || surface_distance(&camera.eye) <= 0.0 {
+                camera.zoom(zoom);
+            } else {
+                scroll_zoom = 0.0;
+            }
+        }
+
+        // Vuelo hacia un planeta: cualquier tecla de movimiento, arrastre del ratón, giro de
+        // la rueda o entrada del mando lo cancela y deja la cámara donde esté. El planeta se
+        // toma en el cuadro que se va a dibujar
+        let camera_keys = [
+            Key::W,
+            Key::S,
+            Key::A,
+            Key::D,
+            Key::R,
+            Key::F,
+            Key::Q,
+            Key::E,
+            Key::Left,
+            Key::Right,
+            Key::Up,
+            Key::Down,
+        ];
+        let mouse_moved = input.mouse_drag() != (0.0, 0.0) || input.scroll() != 0.0;
+        if mouse_moved || pad.is_active() || camera_keys.iter().any(|&key| input.is_key_down(key)) {
+            warp = None;
+        }
+        if let Some((i, animation)) = &mut warp {
+            let target = orbits.planet_position(*i, next_time);
+            if animation.apply(&mut camera, &target, 1.0 / WARP_FRAMES as f32) {
+                warp = None;
             }
         }
 
-        // Rotación de la cámara
-        if window.is_key_down(Key::Left) {
-            camera.orbit(-rotation_speed, 0.0);
+        let camera_moved = camera.has_changed;
+        let view_matrix = camera.view_matrix();
+
+        if input.is_key_pressed(Key::K, KeyRepeat::No) {
+            show_cockpit = !show_cockpit;
         }
-        if window.is_key_down(Key::Right) {
-            camera.orbit(rotation_speed, 0.0);
+        if input.is_key_pressed(Key::X, KeyRepeat::No) {
+            noclip = !noclip;
+            if noclip {
+                println!("Modo espectador: colisiones desactivadas");
+            } else {
+                println!("Modo espectador desactivado: colisiones activas");
+            }
         }
-        if window.is_key_down(Key::Up) {
-            camera.orbit(0.0, -rotation_speed);
+        if input.is_key_pressed(Key::H, KeyRepeat::No) {
+            show_velocity_hud = !show_velocity_hud;
         }
-        if window.is_key_down(Key::Down) {
-            camera.orbit(0.0, rotation_speed);
+        if input.is_key_pressed(Key::I, KeyRepeat::No) {
+            show_inspector = !show_inspector;
         }
+        if let Some(music) = &mut music {
+            if input.is_key_pressed(Key::M, KeyRepeat::No) {
+                match music.next_track() {
+                    Ok(()) => println!("Música: {}", music.track_name()),
+                    Err(err) => eprintln!("{}", err),
+                }
+            }
 
-        // Zoom
-        if window.is_key_down(Key::Q) {
-            camera.zoom(-zoom_speed);
+            let mut volume_delta = 0.0;
+            if input.is_key_pressed(Key::Equal, KeyRepeat::Yes)
+                || input.is_key_pressed(Key::NumPadPlus, KeyRepeat::Yes)
+            {
+                volume_delta += MUSIC_VOLUME_STEP;
+            }
+            if input.is_key_pressed(Key::Minus, KeyRepeat::Yes)
+                || input.is_key_pressed(Key::NumPadMinus, KeyRepeat::Yes)
+            {
+                volume_delta -= MUSIC_VOLUME_STEP;
+            }
+            if volume_delta != 0.0 {
+                music.set_volume(music.volume() + volume_delta);
+                println!("Volumen de la música: {:.0}%", music.volume() * 100.0);
+            }
+        }
+        if input.is_key_pressed(Key::F1, KeyRepeat::No) {
+            show_light_gizmos = !show_light_gizmos;
+        }
+        if input.is_key_pressed(Key::F2, KeyRepeat::No) {
+            show_frame_stats = !show_frame_stats;
+        }
+        if input.is_key_pressed(Key::F3, KeyRepeat::No) {
+            bloom_enabled = !bloom_enabled;
+        }
+        if input.is_key_pressed(Key::F5, KeyRepeat::No) {
+            show_minimap = !show_minimap;
+        }
+        if input.is_key_pressed(Key::Z, KeyRepeat::No) {
+            show_depth = !show_depth;
+        }
+        if input.is_key_pressed(Key::F4, KeyRepeat::No) {
+            trail = match trail {
+                Some(_) => None,
+                None => Some(Vec::new()),
+            };
+        }
+        if input.is_key_pressed(Key::G, KeyRepeat::No) {
+            framebuffer.render_mode = match framebuffer.render_mode {
+                RenderMode::Filled => RenderMode::Wireframe,
+                RenderMode::Wireframe => RenderMode::Filled,
+            };
+        }
+        if input.is_key_pressed(Key::F6, KeyRepeat::No) {
+            framebuffer.shading_mode = match framebuffer.shading_mode {
+                ShadingMode::Smooth => ShadingMode::Flat,
+                ShadingMode::Flat => ShadingMode::Smooth,
+            };
+        }
+        if input.is_key_pressed(Key::O, KeyRepeat::No) {
+            framebuffer.overdraw = match framebuffer.overdraw {
+                Some(_) => None,
+                None => Some(Vec::new()),
+            };
+        }
+        if input.is_key_pressed(Key::N, KeyRepeat::No) {
+            label_mode = label_mode.next();
+            println!("Etiquetas: {:?}", label_mode);
+        }
+        if input.is_key_pressed(Key::L, KeyRepeat::No) {
+            god_rays_enabled = !god_rays_enabled;
+        }
+        if input.is_key_pressed(Key::T, KeyRepeat::No) {
+            world.tone_map = world.tone_map.next();
+            println!("Mapeo de tonos: {:?}", world.tone_map);
+        }
+        let mut exposure_delta = 0.0;
+        if input.is_key_pressed(Key::Period, KeyRepeat::Yes) {
+            exposure_delta += SKYBOX_EXPOSURE_STEP;
+        }
+        if input.is_key_pressed(Key::Comma, KeyRepeat::Yes) {
+            exposure_delta -= SKYBOX_EXPOSURE_STEP;
+        }
+        if exposure_delta != 0.0 {
+            world.skybox_exposure =
+                (world.skybox_exposure + exposure_delta).clamp(0.0, MAX_SKYBOX_EXPOSURE);
+            println!("Brillo del fondo: {:.1}", world.skybox_exposure);
+        }
+        let mut ambient_delta = 0.0;
+        if input.is_key_pressed(Key::PageUp, KeyRepeat::Yes) {
+            ambient_delta += AMBIENT_LIGHT_STEP;
+        }
+        if input.is_key_pressed(Key::PageDown, KeyRepeat::Yes) {
+            ambient_delta -= AMBIENT_LIGHT_STEP;
         }
-        if window.is_key_down(Key::E) {
-            camera.zoom(zoom_speed);
+        if ambient_delta != 0.0 {
+            world.ambient_light = (world.ambient_light + ambient_delta).clamp(0.0, 1.0);
+            println!("Luz ambiente: {:.2}", world.ambient_light);
+        }
+        if input.is_key_pressed(Key::J, KeyRepeat::No) {
+            world.asteroid_belt = generate_belt(world.asteroid_belt.next_seed());
+        }
+        if input.is_key_pressed(Key::B, KeyRepeat::No) {
+            motion_blur_enabled = !motion_blur_enabled;
+            motion_blur.reset();
         }
 
-        let view_matrix = look_at(&camera.eye, &camera.center, &camera.up);
+        // Plano cercano adaptativo: se recalcula la proyección según el cuerpo más cercano
+        if args.adaptive_near {
+            let moon_parent = scene::moon_parent(orbits.planets());
+            let moon_position = scene::moon_position(&planet_positions[moon_parent], time);
+            let surface_distance = nearest_surface_distance(
+                &camera.eye,
+                orbits.planets(),
+                &planet_positions,
+                &moon_position,
+                world.sphere_radius,
+                world.moon_radius,
+            );
+            projection_matrix = create_perspective_matrix(
+                window_width as f32,
+                window_height as f32,
+                projection.fov_deg,
+                adaptive_near_plane(surface_distance, projection.near),
+                projection.far,
+            );
+        }
 
-        let distance_to_center = (camera.eye - Vec3::new(0.0, 0.0, 0.0)).magnitude();
-        let visibility_factor = calculate_visibility_factor(distance_to_center, 30.0, 70.0);
+        // Congelar la vista actual como cámara secundaria para visualizar su frustum
+        if input.is_key_pressed(Key::V, KeyRepeat::No) {
+            frozen_view_projection = match frozen_view_projection {
+                Some(_) => None,
+                None => Some(projection_matrix * view_matrix),
+            };
+        }
 
-        time += 1;
-        framebuffer.clear();
-        for z in framebuffer.zbuffer.iter_mut() {
-            *z = f32::INFINITY;
+        time = next_time;
+        let render_start = Instant::now();
+        // Con supermuestreo la escena va a su framebuffer, con los modos de dibujo que las
+        // teclas cambian en el de la ventana
+        let scene_framebuffer = match &mut supersampled {
+            Some(supersampled) => {
+                supersampled.render_mode = framebuffer.render_mode;
+                supersampled.shading_mode = framebuffer.shading_mode;
+                supersampled.edge_antialiasing = framebuffer.edge_antialiasing;
+                if supersampled.overdraw.is_some() != framebuffer.overdraw.is_some() {
+                    supersampled.overdraw = framebuffer.overdraw.as_ref().map(|_| Vec::new());
+                }
+                supersampled
+            }
+            None => &mut framebuffer,
+        };
+        match &trail {
+            Some(previous) if previous.len() == scene_framebuffer.buffer.len() => {
+                scene_framebuffer.buffer.copy_from_slice(previous);
+                scene_framebuffer.fade(TRAIL_FADE);
+                // La estela es sólo de color: la profundidad y el conteo se reinician igual
+                scene_framebuffer.clear_depth();
+                if let Some(overdraw) = &mut scene_framebuffer.overdraw {
+                    overdraw.fill(0);
+                }
+            }
+            _ => scene_framebuffer.clear(),
+        }
+
+        // Con la cabina activa estamos "dentro" de la nave, así que no se dibuja su modelo.
+        // Con estelas no hay fondo: taparía cada cuadro lo que queda del anterior
+        render_scene(
+            scene_framebuffer,
+            &world,
+            &camera,
+            &orbits,
+            time,
+            view_matrix,
+            projection_matrix,
+            !show_cockpit,
+            trail.is_none(),
+        );
+        if let Some(previous) = &mut trail {
+            previous.clone_from(&scene_framebuffer.buffer);
+        }
+        if let Some(supersampled) = &supersampled {
+            framebuffer.downsample_from(supersampled, ssaa);
         }
 
-        // Renderizar el skybox
         let base_uniforms = Uniforms {
             model_matrix: Mat4::identity(),
             view_matrix,
             projection_matrix,
             viewport_matrix,
             time,
-            noise: fastnoise_lite::FastNoiseLite::new(),
+            noise: &world.noise,
+            camera_eye: camera.eye,
+            center: Vec3::new(0.0, 0.0, 0.0),
+            occluder: None,
+            emissive: false,
+            textures: &world.planet_textures,
+            ambient: world.ambient_light,
+            tone_map: world.tone_map,
         };
 
-        render_skybox(&mut framebuffer, &camera, &skybox_texture, &base_uniforms);
+        if let Some(view_projection) = &frozen_view_projection {
+            render_frustum(&mut framebuffer, view_projection, &base_uniforms);
+        }
+        if show_light_gizmos {
+            render_light_gizmos(&mut framebuffer, &orbits, world.sphere_radius, &base_uniforms);
+        }
 
-        let ship_offset = 15.0;
-        let ship_position = camera.eye + (camera.center - camera.eye).normalize() * ship_offset;
-        let ship_rotation_angle = std::f32::consts::PI;
+        let planets = orbits.planets();
+        for body in scene::bodies(planets).filter(|&body| body != Body::Sun) {
+            let angle = body.orbital_angle(&orbits, time);
+            if let Some(text) = hud::planet_label(body.name(planets), label_mode, angle) {
+                let lift = Vec3::new(0.0, body.scale(planets) * 0.6, 0.0);
+                let top = body.position(&orbits, time) + lift;
+                if let Some(screen) = project_to_screen(&top, &base_uniforms) {
+                    hud::draw_label(&mut framebuffer, screen.x, screen.y - 4.0, &text, 0xFFFFFF);
+                }
+            }
+        }
 
-        let ship_uniforms = Uniforms {
-            model_matrix: create_model_matrix(ship_position, 0.1, ship_rotation_angle),
-            view_matrix,
-            projection_matrix,
-            viewport_matrix,
-            time,
-            noise: fastnoise_lite::FastNoiseLite::new(),
-        };
-        render(
-            &mut framebuffer,
-            &ship_uniforms,
-            &vertex_arrays_ship,
-            &ShaderType::Spaceship,
-        );
+        // El radio del sol en pantalla sale del mismo radio con el que se dibuja
+        if god_rays_enabled {
+            let sun_center = Vec3::new(0.0, 0.0, 0.0);
+            let camera_right = (camera.center - camera.eye).cross(&camera.up).normalize();
+            if let (Some(sun_screen), Some(sun_edge)) = (
+                project_to_screen(&sun_center, &base_uniforms),
+                project_to_screen(&(sun_center + camera_right * sun_radius), &base_uniforms),
+            ) {
+                let screen_radius = (sun_edge.xy() - sun_screen.xy()).magnitude();
+                postprocess::apply_god_rays(
+                    &mut framebuffer,
+                    sun_screen.x,
+                    sun_screen.y,
+                    screen_radius,
+                );
+            }
+        }
 
-        let sun_rotation_speed = 0.0001;
-        let sun_rotation = time as f32 * sun_rotation_speed;
+        if bloom_enabled {
+            framebuffer.apply_bloom(BLOOM_THRESHOLD, BLOOM_RADIUS, BLOOM_INTENSITY);
+        }
 
-        // Renderizado del sol
-        let sun_uniforms = Uniforms {
-            model_matrix: create_model_matrix(Vec3::new(0.0, 0.0, 0.0), 10.0, sun_rotation),
-            view_matrix,
-            projection_matrix,
-            viewport_matrix,
-            time,
-            noise: fastnoise_lite::FastNoiseLite::new(),
-        };
-        render(
-            &mut framebuffer,
-            &sun_uniforms,
-            &vertex_arrays_sphere,
-            &ShaderType::Solar,
-        );
+        postprocess::apply_overdraw_heatmap(&mut framebuffer);
 
-        let orbit_visibility_threshold = 10.0;
+        // Velocidad de la cámara en este cuadro: traslación más giro de la dirección de vista
+        let view_direction = (camera.center - camera.eye).normalize();
+        let turn_angle = previous_direction.dot(&view_direction).clamp(-1.0, 1.0).acos();
+        let velocity = camera.eye - previous_eye;
+        let blur_speed = velocity.magnitude() + turn_angle * MOTION_BLUR_TURN_WEIGHT;
+        previous_eye = camera.eye;
+        previous_direction = view_direction;
 
-        for (i, &radio) in orbital_radii.iter().enumerate() {
-            let distance_to_camera = (camera.eye - Vec3::new(0.0, 0.0, 0.0)).magnitude();
+        if motion_blur_enabled {
+            motion_blur.apply(&mut framebuffer, blur_speed);
+        }
 
-            let orbital_speed = orbital_speeds[i];
-            let planet_x = radio * (time as f32 * orbital_speed).cos();
-            let planet_z = radio * (time as f32 * orbital_speed).sin();
-            let planet_position = Vec3::new(planet_x, 0.0, planet_z);
+        // Vista de profundidad: reemplaza la escena ya sombreada; el HUD se dibuja encima
+        if show_depth {
+            framebuffer.render_depth_to_color(
+                near_plane(&projection_matrix),
+                linear_depth(1.0, &projection_matrix),
+            );
+        }
 
-            let current_planet_x = planet_position.x;
-            let current_planet_z = planet_position.z;
+        if show_cockpit {
+            hud::render_cockpit(&mut framebuffer);
+        }
 
-            let planet_scales = vec![2.5, 3.0, 4.0, 5.0, 4.5, 5.0];
-            let planet_scale = planet_scales[i];
-            let speeds_rotation = vec![0.035, 0.035, 0.038, 0.028, 0.028, 0.026];
-            let to_sun = Vec3::new(0.0, 0.0, 0.0) - planet_position;
-            let alignment_angle = to_sun.normalize().dot(&Vec3::y_axis());
-            let planet_rotation = alignment_angle + (time as f32 * speeds_rotation[i]);
+        let frame_elapsed = last_frame.elapsed().as_secs_f32();
+        let frame_time = frame_elapsed.min(MAX_FRAME_TIME);
+        last_frame = Instant::now();
+        if show_velocity_hud {
+            hud::render_velocity_hud(
+                &mut framebuffer,
+                &camera.eye,
+                &velocity,
+                frame_time,
+                &base_uniforms,
+            );
+        }
 
-            // Verificar si el planeta está en el frustum
-            if is_in_frustum(
-                &planet_position,
-                planet_scale,
-                &view_matrix,
-                &projection_matrix,
-            ) {
-                // Renderizar planeta
-                let planet_uniforms = Uniforms {
-                    model_matrix: create_model_matrix(
-                        planet_position,
-                        planet_scale,
-                        planet_rotation,
-                    ),
-                    view_matrix,
-                    projection_matrix,
-                    viewport_matrix,
-                    time,
-                    noise: fastnoise_lite::FastNoiseLite::new(),
-                };
-
-                render(
-                    &mut framebuffer,
-                    &planet_uniforms,
-                    &vertex_arrays_sphere,
-                    &shaders[i],
-                );
+        if show_minimap {
+            hud::render_minimap(&mut framebuffer, orbits.planets(), &planet_positions, &camera);
+        }
 
-                // Renderizar órbita solo si la cámara está lo suficientemente lejos
-                if distance_to_camera > radio + orbit_visibility_threshold {
-                    let orbit_scale = 0.1;
-                    if is_in_frustum(
-                        &Vec3::new(0.0, 0.0, 0.0),
-                        radio + orbit_scale,
-                        &view_matrix,
-                        &projection_matrix,
-                    ) {
-                        render_orbit_lines(
-                            &mut framebuffer,
-                            radio,
-                            Color::new(128, 128, 128, 255),
-                            150,
-                            &base_uniforms,
-                            visibility_factor
-                        );
-                    }
-                }
+        if world.skybox_exposure != args.skybox_exposure {
+            hud::render_skybox_exposure(&mut framebuffer, world.skybox_exposure);
+        }
+        if world.ambient_light != args.ambient_light {
+            hud::render_ambient_light(&mut framebuffer, world.ambient_light);
+        }
 
-                // Renderizar luna solo para el primer planeta
-                if i == 0 {
-                    let orbit_radius_moon = 2.0;
-                    let orbit_speed_moon = 0.09;
-                    let moon_x = current_planet_x
-                        + orbit_radius_moon * (time as f32 * orbit_speed_moon).cos();
-                    let moon_z = current_planet_z
-                        + orbit_radius_moon * (time as f32 * orbit_speed_moon).sin();
-                    let moon_position = Vec3::new(moon_x, 0.0, moon_z);
-
-                    let moon_rotation_speed = 0.005;
-                    let moon_rotation = time as f32 * moon_rotation_speed;
-
-                    if is_in_frustum(&moon_position, 0.5, &view_matrix, &projection_matrix) {
-                        let moon_uniforms = Uniforms {
-                            model_matrix: create_model_matrix(moon_position, 0.5, moon_rotation),
-                            view_matrix,
-                            projection_matrix,
-                            viewport_matrix,
-                            time,
-                            noise: fastnoise_lite::FastNoiseLite::new(),
-                        };
-
-                        render(
-                            &mut framebuffer,
-                            &moon_uniforms,
-                            &vertex_arrays_moon,
-                            &ShaderType::Moon,
-                        );
-                    }
-                }
+        if let (true, Some(body)) = (show_inspector, selected_body) {
+            let shader = match body {
+                Body::Sun => ShaderType::Solar,
+                Body::Planet(i) => orbits.planets()[i].shader.clone(),
+                Body::Moon => ShaderType::Moon,
+            };
+            hud::render_body_inspector(
+                &mut framebuffer,
+                body,
+                &orbits,
+                time,
+                &shader,
+                &camera.eye,
+            );
+        }
+
+        // El tiempo de renderizado cubre la escena, el posprocesado y el HUD; el intervalo
+        // entre cuadros incluye además la espera, así que marca los FPS reales
+        frame_stats.update(frame_elapsed, render_start.elapsed().as_secs_f32());
+        if show_frame_stats {
+            hud::render_frame_stats(&mut framebuffer, &frame_stats);
+        }
+
+        if input.is_key_pressed(Key::Backslash, KeyRepeat::No) {
+            print_debug_state(&camera, &base_uniforms);
+        }
+
+        if input.is_key_pressed(Key::P, KeyRepeat::No) {
+            // El tiempo de simulación se detiene en pausa; la hora del sistema en milisegundos
+            // evita que varias capturas del mismo cuadro se sobrescriban
+            let timestamp = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_millis());
+            let path = format!("captura_{}_{}.ppm", time, timestamp);
+            match framebuffer.save_ppm(&path) {
+                Ok(()) => println!("Captura guardada en {}", path),
+                Err(err) => eprintln!("No se pudo guardar la captura {}: {}", path, err),
             }
         }
+
         window
-            .update_with_buffer(&framebuffer.buffer, framebuffer_width, framebuffer_height)
+            .update_with_buffer(&framebuffer.buffer, framebuffer.width, framebuffer.height)
             .unwrap();
-        std::thread::sleep(frame_delay);
+
+        // Sin cambios que mostrar se baja la frecuencia; la decisión se toma con la entrada
+        // de este cuadro, así que el primero tras cualquier cambio ya se dibuja a ritmo normal
+        let idle = paused && !camera_moved && !input.has_activity();
+        std::thread::sleep(if idle { IDLE_FRAME_DELAY } else { frame_delay });
+    }
+
+    if let Err(err) = input.stop_recording() {
+        eprintln!("Error al guardar la grabación: {}", err);
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BACKGROUND: u32 = 0x204060;
+
+    // Framebuffer de 20x20 relleno de BACKGROUND con la región de recorte en el centro
+    fn scissored_framebuffer() -> (Framebuffer, Rect) {
+        let region = Rect {
+            x: 5,
+            y: 5,
+            width: 10,
+            height: 10,
+        };
+        let mut framebuffer = Framebuffer::new(20, 20);
+        framebuffer.buffer.fill(BACKGROUND);
+        framebuffer.scissor = Some(region);
+        (framebuffer, region)
+    }
+
+    fn assert_only_inside_changed(framebuffer: &Framebuffer, region: &Rect) {
+        let mut changed_inside = false;
+        for y in 0..framebuffer.height {
+            for x in 0..framebuffer.width {
+                let changed = framebuffer.get_color(x, y) != BACKGROUND;
+                if region.contains(x, y) {
+                    changed_inside |= changed;
+                } else {
+                    assert!(!changed, "({}, {}) fuera de la región cambió", x, y);
+                }
+            }
+        }
+        assert!(changed_inside);
+    }
+
+    #[test]
+    fn world_lines_stay_inside_the_scissor() {
+        let projection = create_perspective_matrix(20.0, 20.0, 45.0, 0.1, 100.0);
+
+        // Trazo opaco, y con el z-buffer justo detrás para que la línea se desvanezca
+        for stored_depth in [f32::INFINITY, 0.9] {
+            let (mut framebuffer, region) = scissored_framebuffer();
+            framebuffer.zbuffer.fill(stored_depth);
+            line_with_depth_fade(&mut framebuffer, 0, 0, 19, 19, 0.5, 0.5, 1.0, &projection);
+            line_with_depth_fade(&mut framebuffer, 19, 0, 0, 19, 0.5, 0.5, 1.0, &projection);
+            assert_only_inside_changed(&framebuffer, &region);
+        }
+
+        let (mut framebuffer, region) = scissored_framebuffer();
+        line_aa(&mut framebuffer, 0.0, 0.0, 19.0, 13.0, 0.5, 0.5, 0xFFFFFF, 1.0);
+        line_aa(&mut framebuffer, 2.0, 19.0, 17.0, 0.0, 0.5, 0.5, 0xFFFFFF, 1.0);
+        assert_only_inside_changed(&framebuffer, &region);
+
+        let (mut framebuffer, region) = scissored_framebuffer();
+        line_with_depth(&mut framebuffer, 0, 10, 19, 10, 0.5, 0.5);
+        assert_only_inside_changed(&framebuffer, &region);
+    }
+
+    #[test]
+    fn triangles_stay_inside_the_scissor() {
+        // Cuadrado que cubre toda la pantalla en NDC, con ambos sentidos de giro para que
+        // el descarte de caras traseras no deje la prueba vacía
+        let corner = |x: f32, y: f32| {
+            Vertex::new(Vec3::new(x, y, 0.0), Vec3::new(0.0, 0.0, 1.0), Vec2::new(0.0, 0.0))
+        };
+        let corners = [
+            corner(-1.0, -1.0),
+            corner(1.0, -1.0),
+            corner(1.0, 1.0),
+            corner(-1.0, 1.0),
+        ];
+        let quad: Vec<Vertex> = [[0, 1, 2], [0, 2, 3], [0, 2, 1], [0, 3, 2]]
+            .iter()
+            .flatten()
+            .map(|&i| corners[i].clone())
+            .collect();
+
+        let noise = FastNoiseLite::new();
+        for edge_aa in [false, true] {
+            let (mut framebuffer, region) = scissored_framebuffer();
+            framebuffer.edge_antialiasing = edge_aa;
+            let uniforms = Uniforms {
+                model_matrix: Mat4::identity(),
+                view_matrix: Mat4::identity(),
+                projection_matrix: Mat4::identity(),
+                viewport_matrix: create_viewport_matrix(20.0, 20.0),
+                time: 0,
+                noise: &noise,
+                camera_eye: Vec3::new(0.0, 0.0, 1.0),
+                center: Vec3::new(0.0, 0.0, 0.0),
+                occluder: None,
+                emissive: true,
+                textures: &[],
+                ambient: 1.0,
+                tone_map: ToneMap::Clamp,
+            };
+            render(&mut framebuffer, &uniforms, &quad, &ShaderType::Solar, CULL_CLOCKWISE);
+            assert_only_inside_changed(&framebuffer, &region);
+        }
+    }
+}
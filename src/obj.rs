@@ -1,12 +1,16 @@
 use crate::vertex::Vertex;
 use nalgebra_glm::{Vec2, Vec3};
-use tobj;
+use std::collections::HashMap;
+
+// Nombre que reciben las caras que no están bajo ningún usemtl
+pub const DEFAULT_MATERIAL: &str = "default";
 
 pub struct Obj {
     meshes: Vec<Mesh>,
 }
 
 struct Mesh {
+    material: String,
     vertices: Vec<Vec3>,
     normals: Vec<Vec3>,
     texcoords: Vec<Vec2>,
@@ -15,13 +19,35 @@ struct Mesh {
 
 impl Obj {
     pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        let (models, _) = tobj::load_obj(
-            filename,
+        let source =
+            std::fs::read_to_string(filename).map_err(|_| tobj::LoadError::OpenFileFailed)?;
+
+        // tobj sólo separa y nombra los grupos usemtl si conoce el material, y los .mtl
+        // no siempre vienen con el modelo: en lugar de leer el mtllib se declaran los
+        // materiales a partir de los propios usemtl del archivo
+        let material_names = usemtl_names(&source);
+        let (models, _) = tobj::load_obj_buf(
+            &mut source.as_bytes(),
             &tobj::LoadOptions {
                 single_index: true,
                 triangulate: true,
                 ..Default::default()
             },
+            |_| {
+                let materials = material_names
+                    .iter()
+                    .map(|name| tobj::Material {
+                        name: name.clone(),
+                        ..Default::default()
+                    })
+                    .collect();
+                let map = material_names
+                    .iter()
+                    .enumerate()
+                    .map(|(index, name)| (name.clone(), index))
+                    .collect();
+                Ok((materials, map))
+            },
         )?;
 
         let meshes = models
@@ -29,6 +55,10 @@ impl Obj {
             .map(|model| {
                 let mesh = model.mesh;
                 Mesh {
+                    material: mesh
+                        .material_id
+                        .and_then(|id| material_names.get(id).cloned())
+                        .unwrap_or_else(|| DEFAULT_MATERIAL.to_string()),
                     vertices: mesh
                         .positions
                         .chunks(3)
@@ -49,30 +79,108 @@ impl Obj {
             })
             .collect();
 
-        Ok(Obj { meshes })
+        let mut obj = Obj { meshes };
+        if obj.meshes.iter().any(Mesh::lacks_normals) {
+            obj.compute_smooth_normals();
+        }
+
+        Ok(obj)
+    }
+
+    // Normales suaves para las mallas sin líneas vn: cada vértice recibe el promedio de las
+    // normales de las caras que lo tocan. Se agrupa por posición y no por índice porque un
+    // mismo punto puede repetirse con otras coordenadas de textura
+    pub fn compute_smooth_normals(&mut self) {
+        for mesh in self.meshes.iter_mut().filter(|mesh| mesh.lacks_normals()) {
+            let key = |position: &Vec3| position.map(f32::to_bits);
+            let mut sums: HashMap<_, Vec3> = HashMap::new();
+
+            for face in mesh.indices.chunks_exact(3) {
+                let a = mesh.vertices[face[0] as usize];
+                let b = mesh.vertices[face[1] as usize];
+                let c = mesh.vertices[face[2] as usize];
+                // Sin normalizar: las caras grandes pesan más en el promedio
+                let face_normal = (b - a).cross(&(c - a));
+                for position in [a, b, c] {
+                    *sums.entry(key(&position)).or_insert_with(Vec3::zeros) += face_normal;
+                }
+            }
+
+            mesh.normals = mesh
+                .vertices
+                .iter()
+                .map(|position| {
+                    let sum = sums.get(&key(position)).cloned().unwrap_or_else(Vec3::zeros);
+                    if sum.norm() > f32::EPSILON {
+                        sum.normalize()
+                    } else {
+                        Vec3::new(0.0, 1.0, 0.0)
+                    }
+                })
+                .collect();
+        }
     }
 
     pub fn get_vertex_array(&self) -> Vec<Vertex> {
-        let mut vertices = Vec::new();
+        self.meshes.iter().flat_map(Mesh::vertex_array).collect()
+    }
+
+    // Un arreglo de vértices por material, en el orden en que aparecen en el archivo
+    pub fn get_vertex_arrays_by_material(&self) -> Vec<(String, Vec<Vertex>)> {
+        let mut groups: Vec<(String, Vec<Vertex>)> = Vec::new();
 
         for mesh in &self.meshes {
-            for &index in &mesh.indices {
-                let position = mesh.vertices[index as usize];
-                let normal = mesh
-                    .normals
-                    .get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
-                let tex_coords = mesh
-                    .texcoords
-                    .get(index as usize)
-                    .cloned()
-                    .unwrap_or(Vec2::new(0.0, 0.0));
-
-                vertices.push(Vertex::new(position, normal, tex_coords));
+            let vertices = mesh.vertex_array();
+            match groups.iter_mut().find(|(material, _)| *material == mesh.material) {
+                Some((_, group)) => group.extend(vertices),
+                None => groups.push((mesh.material.clone(), vertices)),
             }
         }
 
+        groups
+    }
+}
+
+impl Mesh {
+    fn lacks_normals(&self) -> bool {
+        self.normals.len() < self.vertices.len()
+    }
+
+    fn vertex_array(&self) -> Vec<Vertex> {
+        let mut vertices = Vec::new();
+
+        for &index in &self.indices {
+            let position = self.vertices[index as usize];
+            let normal = self
+                .normals
+                .get(index as usize)
+                .cloned()
+                .unwrap_or(Vec3::new(0.0, 1.0, 0.0));
+            let tex_coords = self
+                .texcoords
+                .get(index as usize)
+                .cloned()
+                .unwrap_or(Vec2::new(0.0, 0.0));
+
+            vertices.push(Vertex::new(position, normal, tex_coords));
+        }
+
         vertices
     }
-}
\ No newline at end of file
+}
+
+// Nombres distintos de los materiales usados con usemtl, en orden de aparición
+fn usemtl_names(source: &str) -> Vec<String> {
+    let mut names: Vec<String> = Vec::new();
+
+    for line in source.lines() {
+        if let Some(name) = line.trim().strip_prefix("usemtl ") {
+            let name = name.trim();
+            if !name.is_empty() && !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+
+    names
+}
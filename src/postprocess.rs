@@ -0,0 +1,277 @@
+use crate::framebuffer::{blend_colors, Framebuffer};
+use nalgebra_glm::Vec3;
+
+// Desenfoque de movimiento: cuánto pesa el cuadro anterior por unidad de movimiento
+// de la cámara y el máximo permitido para que la escena siga siendo legible.
+const MOTION_BLUR_SCALE: f32 = 0.25;
+const MOTION_BLUR_MAX: f32 = 0.75;
+
+pub struct MotionBlur {
+    previous: Vec<u32>,
+}
+
+impl MotionBlur {
+    pub fn new() -> Self {
+        MotionBlur {
+            previous: Vec::new(),
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.previous.clear();
+    }
+
+    // Mezcla el cuadro actual con el anterior según la velocidad de la cámara
+    pub fn apply(&mut self, framebuffer: &mut Framebuffer, camera_speed: f32) {
+        if self.previous.len() != framebuffer.buffer.len() {
+            self.previous = framebuffer.buffer.clone();
+            return;
+        }
+
+        let strength = (camera_speed * MOTION_BLUR_SCALE).min(MOTION_BLUR_MAX);
+        for (pixel, previous) in framebuffer.buffer.iter_mut().zip(self.previous.iter_mut()) {
+            if strength > 0.0 {
+                *pixel = blend_colors(*pixel, *previous, strength);
+            }
+            *previous = *pixel;
+        }
+    }
+}
+
+// Rayos crepusculares (dispersión de luz en espacio de pantalla desde el sol)
+const GOD_RAYS_SAMPLES: usize = 32;
+const GOD_RAYS_DENSITY: f32 = 0.9;
+const GOD_RAYS_DECAY: f32 = 0.95;
+const GOD_RAYS_WEIGHT: f32 = 0.6;
+const GOD_RAYS_EXPOSURE: f32 = 0.08;
+const GOD_RAYS_THRESHOLD: f32 = 0.55;
+const GOD_RAYS_COLOR: (f32, f32, f32) = (1.0, 0.85, 0.55);
+
+fn luminance(color: u32) -> f32 {
+    let r = ((color >> 16) & 0xFF) as f32 / 255.0;
+    let g = ((color >> 8) & 0xFF) as f32 / 255.0;
+    let b = (color & 0xFF) as f32 / 255.0;
+    0.2126 * r + 0.7152 * g + 0.0722 * b
+}
+
+// Acumula luz a lo largo del rayo de cada píxel hacia el sol. La máscara de luz sólo
+// contiene los píxeles brillantes del disco solar que no quedaron tapados en el z-buffer,
+// así que un planeta delante del sol proyecta sombras en los rayos. Se calcula a media
+// resolución para abaratar el muestreo.
+pub fn apply_god_rays(framebuffer: &mut Framebuffer, sun_x: f32, sun_y: f32, sun_radius: f32) {
+    let width = framebuffer.width;
+    let height = framebuffer.height;
+
+    // Desvanecer cuando el sol sale de la pantalla
+    let ndc_x = sun_x / width as f32 * 2.0 - 1.0;
+    let ndc_y = sun_y / height as f32 * 2.0 - 1.0;
+    let outside = (ndc_x.abs().max(ndc_y.abs()) - 1.0).max(0.0);
+    let fade = 1.0 - (outside / 0.5).min(1.0);
+    if fade <= 0.0 {
+        return;
+    }
+
+    let half_width = width.div_ceil(2);
+    let half_height = height.div_ceil(2);
+    let mut mask = vec![0.0; half_width * half_height];
+    let mut has_light = false;
+
+    for hy in 0..half_height {
+        for hx in 0..half_width {
+            let x = (hx * 2).min(width - 1);
+            let y = (hy * 2).min(height - 1);
+            let index = y * width + x;
+            let dx = x as f32 - sun_x;
+            let dy = y as f32 - sun_y;
+            let in_disc = dx * dx + dy * dy <= sun_radius * sun_radius;
+            let is_geometry = framebuffer.zbuffer[index] < f32::MAX;
+            if in_disc && is_geometry {
+                let lum = luminance(framebuffer.buffer[index]);
+                if lum > GOD_RAYS_THRESHOLD {
+                    mask[hy * half_width + hx] = lum;
+                    has_light = true;
+                }
+            }
+        }
+    }
+
+    if !has_light {
+        return;
+    }
+
+    let sun_hx = sun_x / 2.0;
+    let sun_hy = sun_y / 2.0;
+    let mut rays = vec![0.0; half_width * half_height];
+
+    for hy in 0..half_height {
+        for hx in 0..half_width {
+            let step_x = (sun_hx - hx as f32) * GOD_RAYS_DENSITY / GOD_RAYS_SAMPLES as f32;
+            let step_y = (sun_hy - hy as f32) * GOD_RAYS_DENSITY / GOD_RAYS_SAMPLES as f32;
+            let mut sx = hx as f32;
+            let mut sy = hy as f32;
+            let mut decay = 1.0;
+            let mut sum = 0.0;
+
+            for _ in 0..GOD_RAYS_SAMPLES {
+                sx += step_x;
+                sy += step_y;
+                if sx >= 0.0 && sy >= 0.0 {
+                    let (ix, iy) = (sx as usize, sy as usize);
+                    if ix < half_width && iy < half_height {
+                        sum += mask[iy * half_width + ix] * decay * GOD_RAYS_WEIGHT;
+                    }
+                }
+                decay *= GOD_RAYS_DECAY;
+            }
+
+            rays[hy * half_width + hx] = sum;
+        }
+    }
+
+    for y in 0..height {
+        for x in 0..width {
+            let light = rays[(y / 2) * half_width + x / 2] * GOD_RAYS_EXPOSURE * fade;
+            if light <= 0.0 {
+                continue;
+            }
+            if !framebuffer.in_scissor(x, y) {
+                continue;
+            }
+            let index = y * width + x;
+            let pixel = framebuffer.buffer[index];
+            let add = |shift: u32, tint: f32| {
+                let c = ((pixel >> shift) & 0xFF) as f32 + light * tint * 255.0;
+                (c.min(255.0) as u32) << shift
+            };
+            framebuffer.buffer[index] =
+                add(16, GOD_RAYS_COLOR.0) | add(8, GOD_RAYS_COLOR.1) | add(0, GOD_RAYS_COLOR.2);
+        }
+    }
+}
+
+impl Framebuffer {
+    // Resplandor (bloom): los píxeles de geometría por encima del umbral de luminancia se
+    // desenfocan con un gaussiano separable y se suman de nuevo sobre la imagen. El z-buffer
+    // sólo se lee, para que las estrellas del fondo no brillen; se escribe únicamente el
+    // color. Como los rayos, trabaja a media resolución: radius es el radio del núcleo en
+    // píxeles de esa resolución.
+    pub fn apply_bloom(&mut self, threshold: f32, radius: usize, intensity: f32) {
+        let width = self.width;
+        let height = self.height;
+        let half_width = width.div_ceil(2);
+        let half_height = height.div_ceil(2);
+
+        // Paso de brillo: sólo sobrevive lo que supera el umbral, escalado por cuánto lo supera
+        let mut bright = vec![Vec3::zeros(); half_width * half_height];
+        let mut has_light = false;
+        for hy in 0..half_height {
+            for hx in 0..half_width {
+                let index = (hy * 2).min(height - 1) * width + (hx * 2).min(width - 1);
+                if self.zbuffer[index] == f32::MAX {
+                    continue;
+                }
+                let pixel = self.buffer[index];
+                let excess = (luminance(pixel) - threshold) / (1.0 - threshold).max(f32::EPSILON);
+                if excess > 0.0 {
+                    bright[hy * half_width + hx] = unpack(pixel) * excess;
+                    has_light = true;
+                }
+            }
+        }
+        if !has_light {
+            return;
+        }
+
+        let sigma = (radius as f32 / 2.0).max(0.5);
+        let weights: Vec<f32> = (0..=radius)
+            .map(|i| (-((i * i) as f32) / (2.0 * sigma * sigma)).exp())
+            .collect();
+        let total = weights[0] + 2.0 * weights[1..].iter().sum::<f32>();
+
+        // Desenfoque horizontal y luego vertical
+        let blur = |source: &[Vec3], step: (isize, isize)| {
+            let mut target = vec![Vec3::zeros(); source.len()];
+            for hy in 0..half_height {
+                for hx in 0..half_width {
+                    let mut sum = source[hy * half_width + hx] * weights[0];
+                    for (i, weight) in weights.iter().enumerate().skip(1) {
+                        for sign in [-1, 1] {
+                            let sx = hx as isize + step.0 * sign * i as isize;
+                            let sy = hy as isize + step.1 * sign * i as isize;
+                            let inside = sx >= 0
+                                && sy >= 0
+                                && (sx as usize) < half_width
+                                && (sy as usize) < half_height;
+                            if inside {
+                                sum += source[sy as usize * half_width + sx as usize] * *weight;
+                            }
+                        }
+                    }
+                    target[hy * half_width + hx] = sum / total;
+                }
+            }
+            target
+        };
+        let glow = blur(&blur(&bright, (1, 0)), (0, 1));
+
+        for y in 0..height {
+            for x in 0..width {
+                if !self.in_scissor(x, y) {
+                    continue;
+                }
+                let light = glow[(y / 2) * half_width + x / 2] * intensity;
+                if light == Vec3::zeros() {
+                    continue;
+                }
+                let index = y * width + x;
+                self.buffer[index] = pack(unpack(self.buffer[index]) + light);
+            }
+        }
+    }
+}
+
+fn unpack(color: u32) -> Vec3 {
+    Vec3::new(
+        ((color >> 16) & 0xFF) as f32 / 255.0,
+        ((color >> 8) & 0xFF) as f32 / 255.0,
+        (color & 0xFF) as f32 / 255.0,
+    )
+}
+
+fn pack(color: Vec3) -> u32 {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0) as u32;
+    (channel(color.x) << 16) | (channel(color.y) << 8) | channel(color.z)
+}
+
+// Mapa de calor del sobredibujado: de azul (un fragmento) a rojo (OVERDRAW_MAX o más).
+// Los píxeles sin fragmentos quedan en negro.
+const OVERDRAW_MAX: u32 = 8;
+const HEATMAP_COLORS: [(f32, f32, f32); 5] = [
+    (0.0, 0.0, 1.0),
+    (0.0, 1.0, 1.0),
+    (0.0, 1.0, 0.0),
+    (1.0, 1.0, 0.0),
+    (1.0, 0.0, 0.0),
+];
+
+fn heatmap_color(count: u32) -> u32 {
+    if count == 0 {
+        return 0x000000;
+    }
+    let t = (count - 1).min(OVERDRAW_MAX - 1) as f32 / (OVERDRAW_MAX - 1) as f32;
+    let scaled = t * (HEATMAP_COLORS.len() - 1) as f32;
+    let index = (scaled as usize).min(HEATMAP_COLORS.len() - 2);
+    let local = scaled - index as f32;
+    let (a, b) = (HEATMAP_COLORS[index], HEATMAP_COLORS[index + 1]);
+    let channel = |from: f32, to: f32| ((from + (to - from) * local) * 255.0).round() as u32;
+    (channel(a.0, b.0) << 16) | (channel(a.1, b.1) << 8) | channel(a.2, b.2)
+}
+
+pub fn apply_overdraw_heatmap(framebuffer: &mut Framebuffer) {
+    if let Some(overdraw) = &framebuffer.overdraw {
+        for (pixel, &count) in framebuffer.buffer.iter_mut().zip(overdraw.iter()) {
+            *pixel = heatmap_color(count);
+        }
+    }
+}
+
@@ -1,5 +1,6 @@
 use nalgebra_glm::{dot, Vec3};
 
+#[allow(dead_code)]
 pub struct Intersect {
     pub hit: bool,        // Indica si el rayo interceptó un objeto
     pub distance: f32,    // Distancia desde el origen del rayo al punto de intersección
@@ -25,17 +26,38 @@ pub trait RayIntersect {
 }
 
 // Estructura que representa una esfera (usada como skybox)
+#[derive(Clone, Copy)]
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f32,
+    // Giro de la costura de las UV en longitud (radianes)
+    pub uv_offset: f32,
 }
 
 impl Sphere {
     pub fn new(center: Vec3, radius: f32) -> Self {
-        Sphere { center, radius }
+        Sphere {
+            center,
+            radius,
+            uv_offset: 0.0,
+        }
+    }
+
+    pub fn with_uv_offset(mut self, uv_offset: f32) -> Self {
+        self.uv_offset = uv_offset;
+        self
     }
 }
 
+// Coordenadas UV esféricas de una normal. El desplazamiento en longitud mueve la costura
+// (donde u pasa de 1 a 0) y el resultado se envuelve a [0, 1).
+pub fn spherical_uv(normal: &Vec3, longitude_offset: f32) -> (f32, f32) {
+    let longitude = normal.z.atan2(normal.x) + longitude_offset;
+    let u = (0.5 + longitude / (2.0 * std::f32::consts::PI)).rem_euclid(1.0);
+    let v = 0.5 - normal.y.asin() / std::f32::consts::PI;
+    (u, v)
+}
+
 // Implementación de la intersección para una esfera
 impl RayIntersect for Sphere {
     fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
@@ -53,10 +75,187 @@ impl RayIntersect for Sphere {
             let normal = (hit_point - self.center).normalize();
 
             // Calcula las coordenadas UV basadas en la posición en la esfera
-            let u = 0.5 + normal.z.atan2(normal.x) / (2.0 * std::f32::consts::PI);
-            let v = 0.5 - normal.y.asin() / std::f32::consts::PI;
+            let uv = spherical_uv(&normal, self.uv_offset);
+
+            Intersect::new(true, dist, hit_point, normal, uv)
+        }
+    }
+}
+
+// Plano infinito que pasa por `point` (fondo plano o suelo). La escena todavía no lo usa
+#[cfg_attr(not(test), allow(dead_code))]
+pub struct Plane {
+    pub point: Vec3,
+    pub normal: Vec3,
+    // Unidades de mundo que abarca una repetición de la textura
+    pub uv_scale: f32,
+}
+
+#[cfg_attr(not(test), allow(dead_code))]
+impl Plane {
+    pub fn new(point: Vec3, normal: Vec3) -> Self {
+        Plane {
+            point,
+            normal: normal.normalize(),
+            uv_scale: 1.0,
+        }
+    }
 
-            Intersect::new(true, dist, hit_point, normal, (u, v))
+    pub fn with_uv_scale(mut self, uv_scale: f32) -> Self {
+        self.uv_scale = uv_scale;
+        self
+    }
+
+    // Dos ejes tangentes perpendiculares entre sí y a la normal; el auxiliar se elige lejos
+    // de la normal para que el producto cruz no se anule
+    fn tangent_axes(&self) -> (Vec3, Vec3) {
+        let helper = if self.normal.y.abs() < 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let u_axis = helper.cross(&self.normal).normalize();
+        let v_axis = self.normal.cross(&u_axis);
+        (u_axis, v_axis)
+    }
+}
+
+// Implementación de la intersección para un plano
+impl RayIntersect for Plane {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        let miss = Intersect::new(false, 0.0, Vec3::zeros(), Vec3::zeros(), (0.0, 0.0));
+
+        // Rayo paralelo al plano: no lo corta nunca
+        let denominator = dot(&self.normal, ray_direction);
+        if denominator.abs() < 1e-6 {
+            return miss;
         }
+
+        let dist = dot(&(self.point - ray_origin), &self.normal) / denominator;
+        if dist < 0.0 {
+            return miss;
+        }
+
+        let hit_point = ray_origin + ray_direction * dist;
+        // La normal mira hacia el lado desde el que llega el rayo
+        let normal = if denominator > 0.0 { -self.normal } else { self.normal };
+
+        // UV planas: la posición sobre los ejes tangentes, repetida cada uv_scale unidades
+        let (u_axis, v_axis) = self.tangent_axes();
+        let local = hit_point - self.point;
+        let uv = (
+            (dot(&local, &u_axis) / self.uv_scale).rem_euclid(1.0),
+            (dot(&local, &v_axis) / self.uv_scale).rem_euclid(1.0),
+        );
+
+        Intersect::new(true, dist, hit_point, normal, uv)
     }
-}
\ No newline at end of file
+}
+
+// Triángulo en el espacio del mundo; la cara frontal es la de orden antihorario (a, b, c),
+// como en los modelos OBJ
+pub struct Triangle {
+    pub a: Vec3,
+    pub b: Vec3,
+    pub c: Vec3,
+}
+
+impl Triangle {
+    pub fn new(a: Vec3, b: Vec3, c: Vec3) -> Self {
+        Triangle { a, b, c }
+    }
+
+    // Möller–Trumbore. Con `cull_back_faces` se ignoran los impactos por la cara trasera; la
+    // UV devuelta son las coordenadas baricéntricas de b y c
+    pub fn intersect(
+        &self,
+        ray_origin: &Vec3,
+        ray_direction: &Vec3,
+        cull_back_faces: bool,
+    ) -> Intersect {
+        let miss = Intersect::new(false, 0.0, Vec3::zeros(), Vec3::zeros(), (0.0, 0.0));
+
+        let edge1 = self.b - self.a;
+        let edge2 = self.c - self.a;
+        let p = ray_direction.cross(&edge2);
+        // El determinante es positivo cuando el rayo llega por la cara frontal y casi nulo
+        // cuando es paralelo al triángulo
+        let determinant = dot(&edge1, &p);
+        if determinant.abs() < 1e-8 || (cull_back_faces && determinant < 0.0) {
+            return miss;
+        }
+
+        let inverse = 1.0 / determinant;
+        let s = ray_origin - self.a;
+        let u = dot(&s, &p) * inverse;
+        if !(0.0..=1.0).contains(&u) {
+            return miss;
+        }
+
+        let q = s.cross(&edge1);
+        let v = dot(ray_direction, &q) * inverse;
+        if v < 0.0 || u + v > 1.0 {
+            return miss;
+        }
+
+        let dist = dot(&edge2, &q) * inverse;
+        if dist < 0.0 {
+            return miss;
+        }
+
+        let hit_point = ray_origin + ray_direction * dist;
+        let normal = edge1.cross(&edge2).normalize();
+
+        Intersect::new(true, dist, hit_point, normal, (u, v))
+    }
+}
+
+// Implementación de la intersección para un triángulo (por ambas caras)
+impl RayIntersect for Triangle {
+    fn ray_intersect(&self, ray_origin: &Vec3, ray_direction: &Vec3) -> Intersect {
+        self.intersect(ray_origin, ray_direction, false)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f32::consts::PI;
+
+    #[test]
+    fn longitude_offset_shifts_u_and_wraps() {
+        let normal = Vec3::new(1.0, 0.5, 0.3).normalize();
+        let (u, v) = spherical_uv(&normal, 0.0);
+        for offset in [PI / 2.0, PI, 1.5 * PI, 2.0 * PI, -PI / 3.0] {
+            let (shifted_u, shifted_v) = spherical_uv(&normal, offset);
+            let expected = (u + offset / (2.0 * PI)).rem_euclid(1.0);
+            assert!((shifted_u - expected).abs() < 1e-5, "offset {}", offset);
+            assert!((0.0..1.0).contains(&shifted_u));
+            assert_eq!(shifted_v, v);
+        }
+
+        // El meridiano +X está en u = 0.5; media vuelta lo lleva a la costura
+        assert!((spherical_uv(&Vec3::x(), 0.0).0 - 0.5).abs() < 1e-6);
+        assert!((spherical_uv(&Vec3::x(), 0.75 * PI).0 - 0.875).abs() < 1e-6);
+        assert!(spherical_uv(&Vec3::x(), PI).0.abs() < 1e-6);
+    }
+
+    #[test]
+    fn downward_ray_hits_the_ground_plane() {
+        let ground = Plane::new(Vec3::zeros(), Vec3::new(0.0, 2.0, 0.0)).with_uv_scale(4.0);
+        let origin = Vec3::new(1.0, 5.0, 6.0);
+        let hit = ground.ray_intersect(&origin, &Vec3::new(0.0, -1.0, 0.0));
+        assert!(hit.hit);
+        assert!((hit.distance - 5.0).abs() < 1e-6);
+        assert!((hit.point - Vec3::new(1.0, 0.0, 6.0)).magnitude() < 1e-6);
+        assert_eq!(hit.normal, Vec3::new(0.0, 1.0, 0.0));
+        assert!((0.0..1.0).contains(&hit.uv.0) && (0.0..1.0).contains(&hit.uv.1));
+
+        // Desde abajo la normal mira hacia el rayo; hacia arriba o en paralelo no hay corte
+        let below = ground.ray_intersect(&Vec3::new(0.0, -3.0, 0.0), &Vec3::new(0.0, 1.0, 0.0));
+        assert!(below.hit && (below.distance - 3.0).abs() < 1e-6);
+        assert_eq!(below.normal, Vec3::new(0.0, -1.0, 0.0));
+        assert!(!ground.ray_intersect(&origin, &Vec3::new(0.0, 1.0, 0.0)).hit);
+        assert!(!ground.ray_intersect(&origin, &Vec3::new(1.0, 0.0, 0.0)).hit);
+    }
+}
@@ -0,0 +1,202 @@
+use crate::color::Color;
+use crate::framebuffer::Framebuffer;
+use crate::ray_intersect::{RayIntersect, Sphere};
+use crate::shaders::{sun_direction, vertex_shader};
+use crate::triangle;
+use crate::uniforms::Uniforms;
+use crate::vertex::Vertex;
+use fastnoise_lite::FastNoiseLite;
+use nalgebra_glm::{Vec2, Vec3, Vec4};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::f32::consts::PI;
+
+const RING_SEGMENTS: usize = 128;
+const RING_OPACITY: f32 = 0.85;
+// Luz que reciben las partes del anillo a la sombra del planeta
+const RING_AMBIENT: f32 = 0.35;
+const RING_INNER_COLOR: Color = Color::new(150, 130, 105, 0);
+const RING_OUTER_COLOR: Color = Color::new(215, 195, 160, 0);
+// Escala del ruido a lo largo del radio para los ringlets irregulares
+const RINGLET_SCALE: f32 = 1500.0;
+
+// Bandas concéntricas del anillo como intervalos [interior, exterior] en unidades del
+// modelo. Entre bandas quedan divisiones transparentes, al estilo de la de Cassini.
+pub struct RingBands {
+    bands: Vec<(f32, f32)>,
+}
+
+impl RingBands {
+    // El número de bandas, su anchura relativa y el tamaño de cada división salen de la
+    // semilla, así que la misma semilla produce siempre el mismo anillo
+    pub fn generate(seed: u64, inner: f32, outer: f32) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let count = rng.gen_range(2..=5);
+        let span = outer - inner;
+
+        let gaps: Vec<f32> = (1..count).map(|_| rng.gen_range(0.02..0.08) * span).collect();
+        let weights: Vec<f32> = (0..count).map(|_| rng.gen_range(0.5..1.5)).collect();
+        let band_total = span - gaps.iter().sum::<f32>();
+        let weight_total: f32 = weights.iter().sum();
+
+        let mut bands = Vec::with_capacity(count);
+        let mut start = inner;
+        for (i, weight) in weights.iter().enumerate() {
+            let end = start + band_total * weight / weight_total;
+            bands.push((start, end));
+            start = end + gaps.get(i).copied().unwrap_or(0.0);
+        }
+        RingBands { bands }
+    }
+
+    // Índice de la banda que contiene el radio, o None si cae en una división
+    pub fn band_at(&self, radius: f32) -> Option<usize> {
+        self.bands
+            .iter()
+            .position(|&(inner, outer)| radius >= inner && radius <= outer)
+    }
+
+    pub fn inner(&self) -> f32 {
+        self.bands.first().map_or(0.0, |band| band.0)
+    }
+
+    pub fn outer(&self) -> f32 {
+        self.bands.last().map_or(0.0, |band| band.1)
+    }
+}
+
+// Disco con agujero en el plano XZ del modelo, como lista de triángulos
+pub fn ring_mesh(inner: f32, outer: f32) -> Vec<Vertex> {
+    let normal = Vec3::new(0.0, 1.0, 0.0);
+    let point = |radius: f32, angle: f32| {
+        Vertex::new(
+            Vec3::new(radius * angle.cos(), 0.0, radius * angle.sin()),
+            normal,
+            Vec2::new(0.0, 0.0),
+        )
+    };
+
+    let mut vertices = Vec::with_capacity(RING_SEGMENTS * 6);
+    for i in 0..RING_SEGMENTS {
+        let a1 = 2.0 * PI * i as f32 / RING_SEGMENTS as f32;
+        let a2 = 2.0 * PI * (i + 1) as f32 / RING_SEGMENTS as f32;
+        vertices.extend([point(inner, a1), point(outer, a1), point(outer, a2)]);
+        vertices.extend([point(inner, a1), point(outer, a2), point(inner, a2)]);
+    }
+    vertices
+}
+
+// Color del anillo en un radio: tono según la posición en el anillo con estrías finas y
+// ringlets irregulares, como las bandas perturbadas por ruido de los gigantes gaseosos.
+// El ruido depende solo del radio, así que las bandas siguen siendo concéntricas.
+// Devuelve None en las divisiones entre bandas, que se dejan transparentes.
+pub fn ring_color(bands: &RingBands, radius: f32, noise: &FastNoiseLite) -> Option<Color> {
+    let band = bands.band_at(radius)?;
+    let span = (bands.outer() - bands.inner()).max(f32::EPSILON);
+    let t = (radius - bands.inner()) / span;
+    let ringlets = noise.get_noise_2d(radius * RINGLET_SCALE, band as f32 * 100.0);
+    let stripes = 0.85 + 0.1 * (radius * 40.0 + band as f32 * 1.7).sin() + 0.1 * ringlets;
+    Some(RING_INNER_COLOR.lerp(&RING_OUTER_COLOR, t) * stripes)
+}
+
+// Un punto del anillo está a la sombra si el rayo hacia el sol (en el origen) choca con
+// el planeta antes de llegar
+pub fn in_planet_shadow(point: &Vec3, planet: &Sphere) -> bool {
+    let to_sun = -point.normalize();
+    let intersect = planet.ray_intersect(point, &to_sun);
+    intersect.hit && intersect.distance > 0.0
+}
+
+// Dibuja el anillo semitransparente sobre la escena. Respeta el z-buffer (el planeta tapa
+// la mitad trasera) pero no escribe en él, para que lo que está detrás siga viéndose.
+// Se ilumina por ambas caras, así que se ve igual desde encima o debajo del plano orbital,
+// salvo donde el planeta tapa el sol.
+pub fn render_rings(
+    framebuffer: &mut Framebuffer,
+    uniforms: &Uniforms,
+    mesh: &[Vertex],
+    bands: &RingBands,
+    planet: &Sphere,
+) {
+    let transformed: Vec<Vertex> = mesh
+        .iter()
+        .map(|vertex| vertex_shader(vertex, uniforms))
+        .collect();
+
+    let light_dir = sun_direction(uniforms);
+    // Sin antialiasing de bordes: el anillo es translúcido y las aristas compartidas se
+    // mezclarían dos veces
+    for tri in transformed.chunks_exact(3) {
+        let shading = framebuffer.shading_mode;
+        for fragment in triangle::triangle(&tri[0], &tri[1], &tri[2], &light_dir, shading, false) {
+            let x = fragment.position.x as usize;
+            let y = fragment.position.y as usize;
+            if x >= framebuffer.width || y >= framebuffer.height || !framebuffer.in_scissor(x, y) {
+                continue;
+            }
+
+            let index = y * framebuffer.width + x;
+            if let Some(overdraw) = &mut framebuffer.overdraw {
+                overdraw[index] += 1;
+                continue;
+            }
+            if fragment.depth > framebuffer.zbuffer[index] {
+                continue;
+            }
+
+            let local = fragment.vertex_position;
+            let radius = Vec2::new(local.x, local.z).magnitude();
+            let color = match ring_color(bands, radius, uniforms.noise) {
+                Some(color) => color,
+                None => continue,
+            };
+
+            let world = uniforms.model_matrix * Vec4::new(local.x, local.y, local.z, 1.0);
+            let world = Vec3::new(world.x, world.y, world.z);
+            let light = if in_planet_shadow(&world, planet) {
+                RING_AMBIENT
+            } else {
+                let to_sun = -world.normalize();
+                RING_AMBIENT + (1.0 - RING_AMBIENT) * fragment.normal.normalize().dot(&to_sun).abs()
+            };
+
+            framebuffer.blend_pixel(x, y, (color * light).to_hex(), RING_OPACITY);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gaps_between_bands_are_transparent() {
+        let bands = RingBands::generate(7, 1.2, 2.2);
+        let noise = FastNoiseLite::with_seed(1);
+        for pair in bands.bands.windows(2) {
+            let ((inner, outer), (next_inner, _)) = (pair[0], pair[1]);
+            let gap = (outer + next_inner) / 2.0;
+            assert_eq!(bands.band_at(gap), None);
+            assert!(ring_color(&bands, gap, &noise).is_none());
+
+            let band = (inner + outer) / 2.0;
+            assert!(bands.band_at(band).is_some());
+            assert!(ring_color(&bands, band, &noise).is_some());
+        }
+        assert!(ring_color(&bands, bands.inner() - 0.01, &noise).is_none());
+        assert!(ring_color(&bands, bands.outer() + 0.01, &noise).is_none());
+    }
+
+    #[test]
+    fn only_the_far_side_of_the_ring_is_in_the_planet_shadow() {
+        // Sol en el origen y planeta en +X: el anillo detrás del planeta queda a la sombra
+        let planet = Sphere::new(Vec3::new(10.0, 0.0, 0.0), 1.0);
+        let behind = Vec3::new(11.5, 0.0, 0.5);
+        let in_front = Vec3::new(8.5, 0.0, 0.5);
+        assert!(in_planet_shadow(&behind, &planet));
+        assert!(!in_planet_shadow(&in_front, &planet));
+
+        // Detrás del planeta pero fuera de su radio sigue al sol
+        assert!(!in_planet_shadow(&Vec3::new(11.5, 0.0, 2.0), &planet));
+    }
+}
@@ -0,0 +1,448 @@
+use crate::color::Color;
+use crate::shaders::ShaderType;
+use nalgebra_glm::{rotate_x_vec3, Vec3};
+use std::f32::consts::PI;
+
+pub const SUN_NAME: &str = "Sol";
+pub const MOON_NAME: &str = "Luna";
+
+// Datos de un planeta. `radius` es el semieje mayor de la órbita y `speed` la velocidad
+// orbital inicial (se puede cambiar en tiempo de ejecución); `scale` es el tamaño del cuerpo
+pub struct Planet {
+    pub name: String,
+    pub radius: f32,
+    // Excentricidad de la elipse (0 es un círculo) e inclinación del plano orbital en grados,
+    // girado alrededor del eje X
+    pub eccentricity: f32,
+    pub inclination: f32,
+    pub speed: f32,
+    pub scale: f32,
+    pub rotation_speed: f32,
+    // Inclinación del eje de giro en grados, alrededor del eje X (como la de los anillos)
+    pub tilt: f32,
+    pub shader: ShaderType,
+    pub has_moon: bool,
+    // En rotación sincrónica gira una vez por órbita y siempre muestra la misma cara
+    pub tidally_locked: bool,
+    // Con luz propia: se dibuja con su brillo procedural completo en toda la superficie
+    pub emissive: bool,
+    // Halo de atmósfera: tinte y opacidad máxima en el borde (canal a). El halo es una
+    // esfera algo mayor que el planeta, con el radio multiplicado por ATMOSPHERE_SCALE
+    pub atmosphere: Option<Color>,
+}
+
+// Sistema por defecto, usado cuando no hay archivo de sistema (ver system::load_system).
+// Planetas desde el más cercano al sol; añadir uno es añadir una entrada
+pub fn default_planets() -> Vec<Planet> {
+    vec![
+        Planet {
+            name: "Rocoso".to_string(),
+            radius: 15.0,
+            eccentricity: 0.06,
+            inclination: 3.0,
+            speed: 0.04,
+            scale: 2.5,
+            rotation_speed: 0.035,
+            tilt: 23.0,
+            shader: ShaderType::RockyPlanet,
+            has_moon: true,
+            tidally_locked: false,
+            emissive: false,
+            atmosphere: Some(Color::new(230, 160, 110, 90)),
+        },
+        Planet {
+            name: "Rocoso II".to_string(),
+            radius: 25.0,
+            eccentricity: 0.04,
+            inclination: 1.5,
+            speed: 0.017,
+            scale: 3.0,
+            rotation_speed: 0.035,
+            tilt: 5.0,
+            shader: ShaderType::RockyPlanetVariant,
+            has_moon: false,
+            tidally_locked: false,
+            emissive: false,
+            atmosphere: None,
+        },
+        Planet {
+            name: "Gigante Gaseoso".to_string(),
+            radius: 35.0,
+            eccentricity: 0.02,
+            inclination: 1.0,
+            speed: 0.014,
+            scale: 4.0,
+            rotation_speed: 0.038,
+            tilt: 20.0,
+            shader: ShaderType::GasGiant,
+            has_moon: false,
+            tidally_locked: false,
+            emissive: false,
+            atmosphere: Some(Color::new(255, 170, 200, 120)),
+        },
+        Planet {
+            name: "Gigante Helado".to_string(),
+            radius: 45.0,
+            eccentricity: 0.03,
+            inclination: 2.0,
+            speed: 0.03,
+            scale: 5.0,
+            rotation_speed: 0.028,
+            tilt: 98.0,
+            shader: ShaderType::ColdGasGiant,
+            has_moon: false,
+            tidally_locked: false,
+            emissive: false,
+            atmosphere: Some(Color::new(120, 200, 255, 140)),
+        },
+        Planet {
+            name: "Alienigena".to_string(),
+            radius: 55.0,
+            eccentricity: 0.08,
+            inclination: 4.0,
+            speed: 0.010,
+            scale: 4.5,
+            rotation_speed: 0.028,
+            tilt: 12.0,
+            shader: ShaderType::AlienPlanet,
+            has_moon: false,
+            tidally_locked: false,
+            emissive: false,
+            atmosphere: Some(Color::new(110, 255, 190, 120)),
+        },
+        Planet {
+            name: "Glacial".to_string(),
+            radius: 65.0,
+            eccentricity: 0.05,
+            inclination: 2.5,
+            speed: 0.009,
+            scale: 5.0,
+            rotation_speed: 0.026,
+            tilt: 28.0,
+            shader: ShaderType::GlacialTextured,
+            has_moon: false,
+            tidally_locked: false,
+            emissive: false,
+            atmosphere: Some(Color::new(200, 230, 255, 110)),
+        },
+    ]
+}
+
+impl Planet {
+    // Punto de la órbita para un ángulo dado (la anomalía excéntrica): elipse con el sol en
+    // un foco, inclinada alrededor del eje X. Con excentricidad e inclinación 0 es el círculo
+    // de radio `radius` en el plano XZ
+    pub fn orbit_point(&self, angle: f32) -> Vec3 {
+        ellipse_point(self.radius, self.eccentricity, self.inclination, angle)
+    }
+
+    // Distancia máxima al sol (el afelio)
+    pub fn max_distance(&self) -> f32 {
+        self.radius * (1.0 + self.eccentricity)
+    }
+}
+
+// Punto de una elipse con el sol en un foco para una anomalía excéntrica dada, con el plano
+// inclinado `inclination` grados alrededor del eje X
+pub fn ellipse_point(semi_major: f32, eccentricity: f32, inclination: f32, angle: f32) -> Vec3 {
+    let semi_minor = semi_major * (1.0 - eccentricity * eccentricity).sqrt();
+    let x = semi_major * (angle.cos() - eccentricity);
+    let z = semi_minor * angle.sin();
+    let inclination = inclination.to_radians();
+    Vec3::new(x, z * inclination.sin(), z * inclination.cos())
+}
+
+pub const SUN_EMISSIVE: bool = true;
+pub const SUN_SCALE: f32 = 10.0;
+
+pub const ATMOSPHERE_SCALE: f32 = 1.12;
+
+pub const MIN_ORBITAL_SPEED: f32 = 0.0;
+pub const MAX_ORBITAL_SPEED: f32 = 0.2;
+
+// Hay una sola luna y orbita al planeta con `has_moon` (system::load_system comprueba que
+// sea exactamente uno)
+pub fn moon_parent(planets: &[Planet]) -> usize {
+    planets.iter().position(|planet| planet.has_moon).unwrap_or(0)
+}
+
+pub const MOON_ORBIT_RADIUS: f32 = 2.0;
+pub const MOON_ORBIT_SPEED: f32 = 0.09;
+pub const MOON_ROTATION_SPEED: f32 = 0.005;
+pub const MOON_TIDALLY_LOCKED: bool = false;
+pub const MOON_SCALE: f32 = 0.5;
+
+// Anillos: radios como múltiplos del radio del planeta e inclinación respecto al plano
+// orbital. La estructura de bandas sale de la semilla (más el índice del planeta, para que
+// cada planeta con anillos tenga el suyo).
+pub struct RingConfig {
+    pub planet: usize,
+    pub inner: f32,
+    pub outer: f32,
+    pub tilt: f32,
+}
+
+pub const RINGED_PLANETS: [RingConfig; 1] = [RingConfig {
+    planet: 2,
+    inner: 1.3,
+    outer: 2.3,
+    tilt: 20.0 * PI / 180.0,
+}];
+pub const DEFAULT_RING_SEED: u64 = 2602;
+
+// Cinturón de asteroides más allá del último planeta
+pub const ASTEROID_BELT_INNER: f32 = 74.0;
+pub const ASTEROID_BELT_OUTER: f32 = 84.0;
+pub const ASTEROID_COUNT: usize = 400;
+pub const DEFAULT_ASTEROID_SEED: u64 = 1337;
+
+// Cometa en una órbita muy excéntrica. Avanza según la ley de Kepler (más rápido cerca del
+// sol): COMET_MEAN_MOTION es lo que crece la anomalía media en cada cuadro y COMET_PHASE su
+// valor inicial, elegido para que el cometa empiece acercándose al sol
+pub const COMET_SEMI_MAJOR: f32 = 60.0;
+pub const COMET_ECCENTRICITY: f32 = 0.85;
+pub const COMET_INCLINATION: f32 = 12.0;
+pub const COMET_MEAN_MOTION: f32 = 0.002;
+pub const COMET_PHASE: f32 = -0.6;
+pub const COMET_RADIUS: f32 = 0.5;
+
+// Semilla del ruido de los shaders procedurales
+pub const DEFAULT_NOISE_SEED: i32 = 1337;
+
+// Iluminación mínima de la cara nocturna de los cuerpos (0 = negro, 1 = sin sombreado)
+pub const DEFAULT_AMBIENT_LIGHT: f32 = 0.1;
+
+// Cuerpos seleccionables: el sol, cada planeta y la luna se tratan igual al elegir,
+// seguir o inspeccionar
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Body {
+    Sun,
+    Planet(usize),
+    Moon,
+}
+
+// Orden en el que se recorren al cambiar de selección: el sol, los planetas y la luna
+pub fn bodies(planets: &[Planet]) -> impl Iterator<Item = Body> {
+    std::iter::once(Body::Sun)
+        .chain((0..planets.len()).map(Body::Planet))
+        .chain(std::iter::once(Body::Moon))
+}
+
+impl Body {
+    pub fn name(self, planets: &[Planet]) -> &str {
+        match self {
+            Body::Sun => SUN_NAME,
+            Body::Planet(index) => &planets[index].name,
+            Body::Moon => MOON_NAME,
+        }
+    }
+
+    pub fn scale(self, planets: &[Planet]) -> f32 {
+        match self {
+            Body::Sun => SUN_SCALE,
+            Body::Planet(index) => planets[index].scale,
+            Body::Moon => MOON_SCALE,
+        }
+    }
+
+    pub fn position(self, orbits: &OrbitState, time: u32) -> Vec3 {
+        match self {
+            Body::Sun => Vec3::new(0.0, 0.0, 0.0),
+            Body::Planet(index) => orbits.planet_position(index, time),
+            Body::Moon => {
+                let parent = moon_parent(orbits.planets());
+                moon_position(&orbits.planet_position(parent, time), time)
+            }
+        }
+    }
+
+    // Ángulo recorrido en la órbita alrededor de su cuerpo central (el sol no orbita)
+    pub fn orbital_angle(self, orbits: &OrbitState, time: u32) -> f32 {
+        match self {
+            Body::Sun => 0.0,
+            Body::Planet(index) => orbits.orbital_angle(index, time),
+            Body::Moon => moon_orbital_angle(time),
+        }
+    }
+
+    pub fn next(self, planets: &[Planet]) -> Self {
+        let bodies: Vec<Body> = bodies(planets).collect();
+        let index = bodies.iter().position(|&body| body == self).unwrap_or(0);
+        bodies[(index + 1) % bodies.len()]
+    }
+}
+
+pub struct BodyPosition<'a> {
+    pub name: &'a str,
+    pub position: Vec3,
+}
+
+// Planetas de la simulación, cargados una vez al arrancar, con sus velocidades orbitales
+// modificables en tiempo de ejecución. Cada planeta guarda una fase para que su ángulo no
+// salte cuando cambia la velocidad.
+pub struct OrbitState {
+    planets: Vec<Planet>,
+    speeds: Vec<f32>,
+    phases: Vec<f32>,
+}
+
+impl OrbitState {
+    pub fn new(planets: Vec<Planet>) -> Self {
+        OrbitState {
+            speeds: planets.iter().map(|planet| planet.speed).collect(),
+            phases: vec![0.0; planets.len()],
+            planets,
+        }
+    }
+
+    pub fn planets(&self) -> &[Planet] {
+        &self.planets
+    }
+
+    pub fn speed(&self, index: usize) -> f32 {
+        self.speeds[index]
+    }
+
+    pub fn set_speed(&mut self, index: usize, speed: f32, time: u32) -> f32 {
+        let speed = speed.clamp(MIN_ORBITAL_SPEED, MAX_ORBITAL_SPEED);
+        self.phases[index] += time as f32 * (self.speeds[index] - speed);
+        self.speeds[index] = speed;
+        speed
+    }
+
+    pub fn orbital_angle(&self, index: usize, time: u32) -> f32 {
+        time as f32 * self.speeds[index] + self.phases[index]
+    }
+
+    pub fn planet_position(&self, index: usize, time: u32) -> Vec3 {
+        self.planets[index].orbit_point(self.orbital_angle(index, time))
+    }
+
+    pub fn planet_rotation(&self, index: usize, time: u32) -> f32 {
+        let to_sun = -self.planet_position(index, time);
+        let planet = &self.planets[index];
+        if planet.tidally_locked {
+            return tidally_locked_rotation(&to_sun, planet.tilt);
+        }
+        let alignment_angle = to_sun.normalize().dot(&Vec3::y_axis());
+        alignment_angle + time as f32 * planet.rotation_speed
+    }
+
+    // Cuadros que tarda el planeta en dar una vuelta sobre sí mismo (infinito si no gira)
+    pub fn rotation_period(&self, index: usize) -> f32 {
+        let speed = if self.planets[index].tidally_locked {
+            self.speeds[index]
+        } else {
+            self.planets[index].rotation_speed
+        };
+        if speed.abs() <= f32::EPSILON {
+            f32::INFINITY
+        } else {
+            2.0 * PI / speed.abs()
+        }
+    }
+}
+
+// Giro sobre el eje propio que mantiene la cara local -X apuntando hacia `to_center`, con el
+// eje inclinado `tilt` grados alrededor de X como en la matriz de modelo. La dirección se
+// pasa al marco inclinado y se toma su proyección sobre el ecuador, así que vale también en
+// órbitas elípticas o inclinadas; la cara queda tan cerca del centro como permite el eje
+pub fn tidally_locked_rotation(to_center: &Vec3, tilt: f32) -> f32 {
+    let local = rotate_x_vec3(to_center, -tilt.to_radians());
+    local.z.atan2(-local.x)
+}
+
+pub fn moon_orbital_angle(time: u32) -> f32 {
+    time as f32 * MOON_ORBIT_SPEED
+}
+
+pub fn moon_rotation(time: u32) -> f32 {
+    if MOON_TIDALLY_LOCKED {
+        let angle = moon_orbital_angle(time);
+        tidally_locked_rotation(&Vec3::new(-angle.cos(), 0.0, -angle.sin()), 0.0)
+    } else {
+        time as f32 * MOON_ROTATION_SPEED
+    }
+}
+
+pub fn moon_position(parent_position: &Vec3, time: u32) -> Vec3 {
+    let angle = moon_orbital_angle(time);
+    Vec3::new(
+        parent_position.x + MOON_ORBIT_RADIUS * angle.cos(),
+        parent_position.y,
+        parent_position.z + MOON_ORBIT_RADIUS * angle.sin(),
+    )
+}
+
+// Posiciones de todos los cuerpos en un instante dado, sin depender del estado del loop
+pub fn scene_positions(orbits: &OrbitState, time: u32) -> Vec<BodyPosition<'_>> {
+    let mut bodies = vec![BodyPosition {
+        name: SUN_NAME,
+        position: Vec3::new(0.0, 0.0, 0.0),
+    }];
+
+    for (i, planet) in orbits.planets().iter().enumerate() {
+        bodies.push(BodyPosition {
+            name: &planet.name,
+            position: orbits.planet_position(i, time),
+        });
+    }
+
+    let parent = moon_parent(orbits.planets());
+    bodies.push(BodyPosition {
+        name: MOON_NAME,
+        position: moon_position(&orbits.planet_position(parent, time), time),
+    });
+
+    bodies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Mat4, Vec4};
+
+    #[test]
+    fn positions_follow_the_loaded_planets() {
+        let mut planets = default_planets();
+        planets.truncate(2);
+        planets[0].name = "Primero".to_string();
+        let orbits = OrbitState::new(planets);
+
+        let names: Vec<&str> = scene_positions(&orbits, 0).iter().map(|body| body.name).collect();
+        assert_eq!(names, [SUN_NAME, "Primero", orbits.planets()[1].name.as_str(), MOON_NAME]);
+        assert_eq!(bodies(orbits.planets()).count(), 4);
+    }
+
+    #[test]
+    fn tidally_locked_face_keeps_pointing_at_the_orbit_center() {
+        let parent = Vec3::new(10.0, 0.0, -4.0);
+        for time in (0..2000).step_by(37) {
+            let angle = moon_orbital_angle(time);
+            let to_center = Vec3::new(-angle.cos(), 0.0, -angle.sin());
+            let rotation =
+                Mat4::from_axis_angle(&Vec3::y_axis(), tidally_locked_rotation(&to_center, 0.0));
+            let face = rotation * Vec4::new(-1.0, 0.0, 0.0, 0.0);
+            let to_parent = (parent - moon_position(&parent, time)).normalize();
+            assert!((face.xyz() - to_parent).magnitude() < 1e-4, "time {}", time);
+        }
+    }
+
+    #[test]
+    fn tidally_locked_planet_faces_the_sun_on_a_tilted_inclined_ellipse() {
+        let (eccentricity, inclination, tilt) = (0.4, 25.0, 30.0);
+        let axis = rotate_x_vec3(&Vec3::y(), f32::to_radians(tilt));
+        for step in 0..64 {
+            let angle = step as f32 * 2.0 * PI / 64.0;
+            let to_sun = -ellipse_point(40.0, eccentricity, inclination, angle);
+            // Misma composición que la matriz de modelo: inclinación * giro sobre Y
+            let rotation = Mat4::from_axis_angle(&Vec3::x_axis(), tilt.to_radians())
+                * Mat4::from_axis_angle(&Vec3::y_axis(), tidally_locked_rotation(&to_sun, tilt));
+            let face = (rotation * Vec4::new(-1.0, 0.0, 0.0, 0.0)).xyz();
+
+            // La cara apunta a la proyección de la dirección al sol sobre el ecuador
+            let expected = (to_sun - axis * to_sun.dot(&axis)).normalize();
+            assert!((face - expected).magnitude() < 1e-4, "angle {}", angle);
+        }
+    }
+}
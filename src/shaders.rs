@@ -1,9 +1,28 @@
 use crate::color::Color;
 use crate::fragment::Fragment;
+use crate::ray_intersect::RayIntersect;
+use crate::uniforms::Uniforms;
 use crate::vertex::Vertex;
-use crate::Uniforms;
 use nalgebra_glm::{mat4_to_mat3, Mat3, Vec3, Vec4};
-use rand::Rng;
+
+const GLACIAL_GLINT_COLOR: Color = Color::new(255, 255, 245, 0);
+const GLACIAL_GLINT_SHININESS: f32 = 120.0;
+// Oscurecimiento del limbo solar (ley lineal): 0 lo desactiva, 1 deja el borde en negro
+const SUN_LIMB_DARKENING: f32 = 0.6;
+const ALIEN_GLOW_COLOR: Color = Color::new(60, 255, 200, 0);
+const ALIEN_GLOW_INTENSITY: f32 = 0.8;
+// Luz de borde de los planetas rocosos y el alienígena: qué tan pegada a la silueta queda
+// (exponente) y cuánto color suma en el borde mismo
+const RIM_COLOR: Color = Color::new(170, 200, 255, 0);
+const RIM_POWER: f32 = 3.0;
+const RIM_INTENSITY: f32 = 0.35;
+// Halo de atmósfera: cuánto se concentra en el borde y cuánto se ve en el lado nocturno
+const ATMOSPHERE_FALLOFF: f32 = 2.0;
+const ATMOSPHERE_NIGHT_OPACITY: f32 = 0.3;
+// Eclipses: luz que queda a la sombra de otro cuerpo y cuánto se adelanta el origen del
+// rayo hacia el sol sobre la normal, para que la superficie no se tape a sí misma
+pub const ECLIPSE_LIGHT: f32 = 0.1;
+const SHADOW_BIAS: f32 = 1e-3;
 
 #[derive(PartialEq, Debug, Clone)]
 pub enum ShaderType {
@@ -14,8 +33,13 @@ pub enum ShaderType {
     RockyPlanetVariant,
     AlienPlanet,
     GlacialTextured,
+    // Imagen de superficie: índice en Uniforms::textures
+    Textured(usize),
     Moon,
-    Spaceship
+    Spaceship,
+    // Halo translúcido con el tinte de la atmósfera del planeta; se dibuja en
+    // BlendMode::AlphaBlend
+    Atmosphere(Color),
 }
 
 pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
@@ -44,7 +68,8 @@ pub fn vertex_shader(vertex: &Vertex, uniforms: &Uniforms) -> Vertex {
         tex_coords: vertex.tex_coords,
         color: vertex.color,
         transformed_position: Vec3::new(screen_position.x, screen_position.y, screen_position.z),
-        transformed_normal: transformed_normal,
+        transformed_normal,
+        clip_w: w,
     }
 }
 
@@ -57,11 +82,80 @@ pub fn fragment_shader(fragment: &Fragment, uniforms: &Uniforms, shader_type: &S
         ShaderType::RockyPlanetVariant => rocky_planet_variant_shader(fragment, uniforms),
         ShaderType::AlienPlanet => alien_planet_shader(fragment, uniforms),
         ShaderType::GlacialTextured => glacial_textured_shader(fragment, uniforms),
+        ShaderType::Textured(index) => textured_shader(fragment, uniforms, *index),
         ShaderType::Moon => moon_shader(fragment, uniforms),
-        ShaderType::Spaceship => blue_shader(fragment, uniforms)
+        ShaderType::Spaceship => blue_shader(fragment, uniforms),
+        ShaderType::Atmosphere(tint) => atmosphere_shader(fragment, uniforms, *tint),
     }
 }
 
+// Dirección del mundo desde el centro del objeto hacia el sol. El propio sol (centro en el
+// origen) no tiene una dirección definida y recibe una cualquiera: es emisivo
+pub fn sun_direction(uniforms: &Uniforms) -> Vec3 {
+    (Vec3::zeros() - uniforms.center)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vec3::z)
+}
+
+// El fragmento está en sombra si el rayo hacia el sol choca con el cuerpo que lo puede
+// tapar (Uniforms::occluder) antes de llegar
+pub fn in_eclipse(fragment: &Fragment, uniforms: &Uniforms) -> bool {
+    let Some(occluder) = &uniforms.occluder else {
+        return false;
+    };
+    let world_pos = world_position(fragment, uniforms);
+    let origin = world_pos + fragment.normal.normalize() * SHADOW_BIAS;
+    let to_sun = (Vec3::zeros() - origin).normalize();
+    let intersect = occluder.ray_intersect(&origin, &to_sun);
+    intersect.hit && intersect.distance > 0.0
+}
+
+// Posición del fragmento en el espacio del mundo
+fn world_position(fragment: &Fragment, uniforms: &Uniforms) -> Vec3 {
+    let local = fragment.vertex_position;
+    let world = uniforms.model_matrix * Vec4::new(local.x, local.y, local.z, 1.0);
+    Vec3::new(world.x, world.y, world.z)
+}
+
+// Valor pseudoaleatorio en [0, 1) que depende solo de la posición del fragmento y de la
+// semilla del ruido: la misma semilla pinta siempre el mismo patrón, sin parpadeo entre
+// fotogramas. salt separa valores independientes para un mismo fragmento
+fn fragment_hash(position: &Vec3, seed: i32, salt: u32) -> f32 {
+    let mut hash = (seed as u32) ^ salt.wrapping_mul(0x9E37_79B9);
+    for component in [position.x, position.y, position.z] {
+        hash ^= component.to_bits();
+        hash = hash.wrapping_mul(0x85EB_CA6B);
+        hash ^= hash >> 13;
+    }
+    hash = hash.wrapping_mul(0xC2B2_AE35);
+    hash ^= hash >> 16;
+    (hash >> 8) as f32 / (1u32 << 24) as f32
+}
+
+// Término especular de Phong compartido por los shaders
+fn specular(normal: &Vec3, light_dir: &Vec3, view_dir: &Vec3, shininess: f32) -> f32 {
+    let n_dot_l = normal.dot(light_dir);
+    if n_dot_l <= 0.0 {
+        return 0.0;
+    }
+    let reflect_dir = (2.0 * n_dot_l * normal - light_dir).normalize();
+    view_dir.dot(&reflect_dir).max(0.0).powf(shininess)
+}
+
+// Luz de borde: 0 donde la superficie mira a la cámara y 1 en la silueta. No depende del
+// sol, así que también recorta el lado nocturno contra el fondo
+fn rim_factor(normal: &Vec3, view_dir: &Vec3, power: f32) -> f32 {
+    (1.0 - normal.dot(view_dir)).clamp(0.0, 1.0).powf(power)
+}
+
+// Color que suma la luz de borde, visto desde la posición real de la cámara
+fn rim_light(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    let world_pos = world_position(fragment, uniforms);
+    let view_dir = (uniforms.camera_eye - world_pos).normalize();
+    let rim = rim_factor(&fragment.normal.normalize(), &view_dir, RIM_POWER);
+    RIM_COLOR * (rim * RIM_INTENSITY)
+}
+
 pub fn blue_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let base_blue = Color::new(30, 30, 100,0); // Azul oscuro base
     let highlight_blue = Color::new(70, 130, 180, 0); // Azul claro para iluminación
@@ -121,24 +215,20 @@ pub fn moon_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
         final_color = final_color.lerp(&crater_color, (surface_details - 0.8) * 0.5);
     }
 
-    let light_dir = Vec3::new(0.6, 0.8, 0.4).normalize();
-    let normal = position.normalize();
-    let lambertian = light_dir.dot(&normal).max(0.0);
-    let shading_factor = 0.75 + 0.25 * lambertian;
-
-    final_color = final_color * shading_factor;
     final_color * fragment.intensity
 }
 
 
 pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    // Se trabaja en color lineal y Color::from_linear lo pasa a 8 bits al final
     let base_colors = [
-        Vec3::new(110.0 / 255.0, 0.0 / 255.0, 90.0 / 255.0),
-        Vec3::new(160.0 / 255.0, 20.0 / 255.0, 60.0 / 255.0),
-        Vec3::new(130.0 / 255.0, 10.0 / 255.0, 80.0 / 255.0),
-        Vec3::new(180.0 / 255.0, 40.0 / 255.0, 90.0 / 255.0),
-        Vec3::new(140.0 / 255.0, 10.0 / 255.0, 70.0 / 255.0),
-    ];
+        Color::new(110, 0, 90, 0),
+        Color::new(160, 20, 60, 0),
+        Color::new(130, 10, 80, 0),
+        Color::new(180, 40, 90, 0),
+        Color::new(140, 10, 70, 0),
+    ]
+    .map(Color::to_linear);
 
     let time = uniforms.time as f32 * 0.001;
     let dynamic_y = fragment.vertex_position.y + time;
@@ -156,13 +246,17 @@ pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let band_variation = (fragment.vertex_position.y * 10.0).sin() * 0.3;
     let band_index_float = (band_sine + band_variation + 1.0) / 2.0 * (base_colors.len() as f32);
     let band_index = band_index_float as usize % base_colors.len();
-    let mut rng = rand::thread_rng();
-    let random_offset: f32 = rng.gen_range(-0.03..0.03);
+    let seed = uniforms.noise.seed;
+    let random_offset = (fragment_hash(&fragment.vertex_position, seed, 0) - 0.5) * 0.06;
     let base_band_color =
         base_colors[band_index] + Vec3::new(random_offset, random_offset, random_offset);
 
-    // Aumentar la saturación de algunas bandas de forma aleatoria
-    let saturation_boost: f32 = if rng.gen_bool(0.5) { 1.2 } else { 1.0 };
+    // Aumentar la saturación de algunas bandas de forma pseudoaleatoria
+    let saturation_boost: f32 = if fragment_hash(&fragment.vertex_position, seed, 1) < 0.5 {
+        1.2
+    } else {
+        1.0
+    };
     let boosted_band_color = base_band_color * saturation_boost;
 
     // Se elige el siguiente color de banda para suavizar la transición
@@ -209,49 +303,41 @@ pub fn gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
     if spot_noise > 0.75 {
         let mix_factor = (spot_noise - 0.75) / 0.25;
-        let storm_color = Vec3::new(0.95, 0.85, 0.65);
+        let storm_color = Color::new(242, 217, 166, 0).to_linear();
         final_color = final_shaded_color.lerp(&storm_color, mix_factor);
     } else {
         final_color = final_shaded_color;
     }
 
-    let normal = fragment.vertex_position.normalize();
-
-    let light_dir = Vec3::new(0.6, 0.8, 0.4).normalize();
-    let lambertian = light_dir.dot(&normal).max(0.0);
-    let shading_factor = 0.75 + 0.25 * lambertian;
+    let normal = fragment.normal.normalize();
 
-    final_color = final_color * shading_factor;
+    let light_dir = sun_direction(uniforms);
 
     // dispersión atmosférica
     let gradient_shading = 1.0 - (fragment.vertex_position.y.abs() * 0.15);
-    final_color = final_color * gradient_shading;
+    final_color *= gradient_shading;
 
     // reflejos especulares para simular brillos en la atmósfera
-    let view_dir = Vec3::new(0.0, 0.0, 1.0).normalize();
-    let reflect_dir = (2.0 * normal.dot(&light_dir) * normal - light_dir).normalize();
-    let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(10.0);
+    let view_dir = (uniforms.camera_eye - world_position(fragment, uniforms)).normalize();
+    let specular_intensity = specular(&normal, &light_dir, &view_dir, 10.0);
 
-    final_color = final_color + Vec3::new(1.0, 1.0, 1.0) * specular_intensity * 0.15;
+    final_color += Vec3::new(1.0, 1.0, 1.0) * specular_intensity * 0.15;
 
-    final_color = final_color * fragment.intensity;
+    final_color *= fragment.intensity;
 
-    Color::new(
-        (final_color.x * 255.0) as u8,
-        (final_color.y * 255.0) as u8,
-        (final_color.z * 255.0) as u8,
-        0
-    )
+    Color::from_linear(final_color, uniforms.tone_map)
 }
 
 pub fn cold_gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
+    // Se trabaja en color lineal y Color::from_linear lo pasa a 8 bits al final
     let base_colors = [
-        Vec3::new(100.0 / 255.0, 150.0 / 255.0, 180.0 / 255.0),
-        Vec3::new(120.0 / 255.0, 180.0 / 255.0, 200.0 / 255.0),
-        Vec3::new(90.0 / 255.0, 140.0 / 255.0, 170.0 / 255.0),
-        Vec3::new(130.0 / 255.0, 190.0 / 255.0, 210.0 / 255.0),
-        Vec3::new(80.0 / 255.0, 120.0 / 255.0, 160.0 / 255.0),
-    ];
+        Color::new(100, 150, 180, 0),
+        Color::new(120, 180, 200, 0),
+        Color::new(90, 140, 170, 0),
+        Color::new(130, 190, 210, 0),
+        Color::new(80, 120, 160, 0),
+    ]
+    .map(Color::to_linear);
 
     let time = uniforms.time as f32 * 0.001;
     let dynamic_y = fragment.vertex_position.y + time;
@@ -271,12 +357,16 @@ pub fn cold_gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color
     let band_variation = (fragment.vertex_position.y * 10.0).sin() * 0.3;
     let band_index_float = (band_sine + band_variation + 1.0) / 2.0 * (base_colors.len() as f32);
     let band_index = band_index_float as usize % base_colors.len();
-    let mut rng = rand::thread_rng();
-    let random_offset: f32 = rng.gen_range(-0.03..0.03);
+    let seed = uniforms.noise.seed;
+    let random_offset = (fragment_hash(&fragment.vertex_position, seed, 0) - 0.5) * 0.06;
     let base_band_color =
         base_colors[band_index] + Vec3::new(random_offset, random_offset, random_offset);
 
-    let saturation_boost: f32 = if rng.gen_bool(0.5) { 1.2 } else { 1.0 };
+    let saturation_boost: f32 = if fragment_hash(&fragment.vertex_position, seed, 1) < 0.5 {
+        1.2
+    } else {
+        1.0
+    };
     let boosted_band_color = base_band_color * saturation_boost;
 
     let next_band_index = (band_index + 1) % base_colors.len();
@@ -321,41 +411,33 @@ pub fn cold_gas_giant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color
 
     if spot_noise > 0.7 {
         let mix_factor = (spot_noise - 0.7) / 0.3;
-        let storm_color = Vec3::new(0.75, 0.85, 0.95);
+        let storm_color = Color::new(191, 217, 242, 0).to_linear();
         final_color = final_shaded_color.lerp(&storm_color, mix_factor);
     } else {
         final_color = final_shaded_color;
     }
 
-    let normal = fragment.vertex_position.normalize();
+    let normal = fragment.normal.normalize();
 
-    let light_dir = Vec3::new(0.6, 0.8, 0.4).normalize();
-    let lambertian = light_dir.dot(&normal).max(0.0);
-    let shading_factor = 0.75 + 0.25 * lambertian;
-    final_color = final_color * shading_factor;
+    let light_dir = sun_direction(uniforms);
 
     let gradient_shading = 1.0 - (fragment.vertex_position.y.abs() * 0.15);
-    final_color = final_color * gradient_shading;
+    final_color *= gradient_shading;
 
-    let view_dir = Vec3::new(0.0, 0.0, 1.0).normalize();
-    let reflect_dir = (2.0 * normal.dot(&light_dir) * normal - light_dir).normalize();
-    let specular_intensity = view_dir.dot(&reflect_dir).max(0.0).powf(10.0);
-    final_color = final_color + Vec3::new(1.0, 1.0, 1.0) * specular_intensity * 0.15;
+    let view_dir = (uniforms.camera_eye - world_position(fragment, uniforms)).normalize();
+    let specular_intensity = specular(&normal, &light_dir, &view_dir, 10.0);
+    final_color += Vec3::new(1.0, 1.0, 1.0) * specular_intensity * 0.15;
 
-    final_color = final_color * fragment.intensity;
+    final_color *= fragment.intensity;
 
-    Color::new(
-        (final_color.x * 255.0) as u8,
-        (final_color.y * 255.0) as u8,
-        (final_color.z * 255.0) as u8,
-        0
-    )
+    Color::from_linear(final_color, uniforms.tone_map)
 }
 
 pub fn solar_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
-    let bright_color = Color::new(255, 240, 70, 0);
-    let mid_color = Color::new(255, 100, 0, 0);
-    let dark_color = Color::new(70, 10, 0, 0);
+    // En color lineal; Color::from_linear aplica el mapeo de tonos (tecla T) y la gamma
+    let bright_color = Color::new(255, 240, 70, 0).to_linear();
+    let mid_color = Color::new(255, 100, 0, 0).to_linear();
+    let dark_color = Color::new(70, 10, 0, 0).to_linear();
 
     let position = Vec3::new(
         fragment.vertex_position.x,
@@ -405,15 +487,21 @@ pub fn solar_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     let combined_bands = bands_pattern1 + bands_pattern2 + high_freq_noise;
 
     let color = if adjusted_noise + combined_bands > 0.4 {
-        mid_color.lerp(&bright_color, adjusted_noise + combined_bands - 0.4)
+        mid_color.lerp(&bright_color, (adjusted_noise + combined_bands - 0.4).clamp(0.0, 1.0))
     } else {
-        dark_color.lerp(&mid_color, (adjusted_noise + combined_bands) * 2.5)
+        dark_color.lerp(&mid_color, ((adjusted_noise + combined_bands) * 2.5).clamp(0.0, 1.0))
     };
 
     let pulse_effect = 1.0 + 0.15 * ((t * 1.5 + position.x * 0.05).sin());
     let final_color = color * pulse_effect;
 
-    final_color * fragment.intensity
+    // El disco se oscurece hacia el borde según el ángulo entre la normal y la vista
+    let world_pos = world_position(fragment, uniforms);
+    let view_dir = (uniforms.camera_eye - world_pos).normalize();
+    let mu = fragment.normal.normalize().dot(&view_dir).max(0.0);
+    let limb_darkening = 1.0 - SUN_LIMB_DARKENING * (1.0 - mu);
+
+    Color::from_linear(final_color * (fragment.intensity * limb_darkening), uniforms.tone_map)
 }
 
 pub fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -505,7 +593,7 @@ pub fn rocky_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
     ) * 0.1;
     final_color = final_color * (1.0 + depth_variation);
 
-    final_color * fragment.intensity
+    final_color * fragment.intensity + rim_light(fragment, uniforms)
 }
 
 pub fn rocky_planet_variant_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -598,7 +686,7 @@ pub fn rocky_planet_variant_shader(fragment: &Fragment, uniforms: &Uniforms) ->
     ) * 0.1;
     final_color = final_color * (1.0 + depth_variation);
 
-    final_color * fragment.intensity
+    final_color * fragment.intensity + rim_light(fragment, uniforms)
 }
 
 pub fn alien_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -705,7 +793,16 @@ pub fn alien_planet_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
 
     let final_color = illuminated_color.limit_min(50);
 
-    final_color * fragment.intensity
+    // Flora bioluminiscente: las zonas alienígenas brillan en el lado nocturno, empezando
+    // un poco antes del terminador
+    let world_pos = world_position(fragment, uniforms);
+    let light_dir = (Vec3::zeros() - world_pos).normalize();
+    let n_dot_l = fragment.normal.normalize().dot(&light_dir);
+    let night = ((0.1 - n_dot_l) / 0.4).clamp(0.0, 1.0);
+    let patch = ((combined_value - 0.65) / 0.2).clamp(0.0, 1.0);
+    let glow = patch * night * ALIEN_GLOW_INTENSITY;
+
+    final_color * fragment.intensity + ALIEN_GLOW_COLOR * glow + rim_light(fragment, uniforms)
 }
 
 pub fn glacial_textured_shader(fragment: &Fragment, uniforms: &Uniforms) -> Color {
@@ -757,5 +854,31 @@ pub fn glacial_textured_shader(fragment: &Fragment, uniforms: &Uniforms) -> Colo
 
     let final_color = illuminated_color.limit_min(60);
 
-    final_color * fragment.intensity
-}
\ No newline at end of file
+    // Destello especular del sol sobre el hielo
+    let world_pos = world_position(fragment, uniforms);
+    let light_dir = (Vec3::zeros() - world_pos).normalize();
+    let view_dir = (uniforms.camera_eye - world_pos).normalize();
+    let normal = fragment.normal.normalize();
+    let glint = specular(&normal, &light_dir, &view_dir, GLACIAL_GLINT_SHININESS);
+
+    final_color * fragment.intensity + GLACIAL_GLINT_COLOR * glint
+}
+
+// Capa de aire alrededor del planeta: el color es fijo y la opacidad (canal a) crece hacia
+// el borde, donde la vista atraviesa más atmósfera, y baja en el lado de noche
+pub fn atmosphere_shader(fragment: &Fragment, uniforms: &Uniforms, tint: Color) -> Color {
+    let world_pos = world_position(fragment, uniforms);
+    let view_dir = (uniforms.camera_eye - world_pos).normalize();
+    let rim = 1.0 - fragment.normal.normalize().dot(&view_dir).abs();
+    let light = ATMOSPHERE_NIGHT_OPACITY + (1.0 - ATMOSPHERE_NIGHT_OPACITY) * fragment.intensity;
+    let alpha = tint.a as f32 * rim.clamp(0.0, 1.0).powf(ATMOSPHERE_FALLOFF) * light;
+
+    Color::new(tint.r, tint.g, tint.b, alpha.round().clamp(0.0, 255.0) as u8)
+}
+
+// Superficie tomada de una imagen en lugar del shader procedural, muestreada con las UV
+// del modelo y con el mismo sombreado lambertiano que reciben los demás cuerpos
+pub fn textured_shader(fragment: &Fragment, uniforms: &Uniforms, index: usize) -> Color {
+    let (u, v) = fragment.tex_coords;
+    uniforms.textures[index].get_color(u, v) * fragment.intensity
+}
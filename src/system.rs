@@ -0,0 +1,229 @@
+use crate::color::Color;
+use crate::scene::{self, Planet};
+use crate::shaders::ShaderType;
+use serde::Deserialize;
+use std::fs;
+use std::io::ErrorKind;
+
+// Archivo del sistema que se busca si no se indica otro con --system
+pub const DEFAULT_SYSTEM_PATH: &str = "system.toml";
+
+const DEFAULT_ROTATION_SPEED: f32 = 0.03;
+const DEFAULT_ATMOSPHERE_OPACITY: u8 = 120;
+
+// Nombres que acepta el campo `shader` de cada planeta
+const SHADER_NAMES: [(&str, ShaderType); 8] = [
+    ("rocky", ShaderType::RockyPlanet),
+    ("rocky_variant", ShaderType::RockyPlanetVariant),
+    ("gas_giant", ShaderType::GasGiant),
+    ("cold_gas_giant", ShaderType::ColdGasGiant),
+    ("alien", ShaderType::AlienPlanet),
+    ("glacial", ShaderType::GlacialTextured),
+    ("solar", ShaderType::Solar),
+    ("moon", ShaderType::Moon),
+];
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SystemFile {
+    planet: Vec<PlanetEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PlanetEntry {
+    name: String,
+    radius: f32,
+    // Órbita elíptica: excentricidad en [0, 1) e inclinación en grados (0 por defecto)
+    #[serde(default)]
+    eccentricity: f32,
+    #[serde(default)]
+    inclination: f32,
+    speed: f32,
+    scale: f32,
+    rotation_speed: Option<f32>,
+    // Inclinación del eje de giro en grados (0 por defecto)
+    #[serde(default)]
+    tilt: f32,
+    shader: String,
+    #[serde(default)]
+    moon: bool,
+    #[serde(default)]
+    tidally_locked: bool,
+    #[serde(default)]
+    emissive: bool,
+    // Tinte del halo como "#RRGGBB" y su opacidad máxima en el borde (0-255)
+    atmosphere: Option<String>,
+    atmosphere_opacity: Option<u8>,
+}
+
+// Lee el sistema solar de un archivo TOML con una tabla [[planet]] por planeta, en orden
+// desde el sol. Si el archivo no existe se usa el sistema por defecto; si existe pero no es
+// válido se devuelve el error, para no arrancar con un sistema distinto del escrito.
+pub fn load_system(path: &str) -> Result<Vec<Planet>, String> {
+    let text = match fs::read_to_string(path) {
+        Ok(text) => text,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(scene::default_planets()),
+        Err(err) => return Err(format!("No se pudo leer {}: {}", path, err)),
+    };
+    let system: SystemFile =
+        toml::from_str(&text).map_err(|err| format!("{} no es válido: {}", path, err))?;
+
+    if system.planet.is_empty() {
+        return Err(format!("{} no define ningún planeta", path));
+    }
+    // La simulación tiene una sola luna
+    if system.planet.iter().filter(|entry| entry.moon).count() != 1 {
+        return Err(format!(
+            "{}: exactamente un planeta debe tener moon = true",
+            path
+        ));
+    }
+
+    system
+        .planet
+        .into_iter()
+        .map(|entry| planet_from_entry(entry).map_err(|err| format!("{}: {}", path, err)))
+        .collect()
+}
+
+fn planet_from_entry(entry: PlanetEntry) -> Result<Planet, String> {
+    if !(entry.radius > 0.0 && entry.scale > 0.0) {
+        return Err(format!(
+            "{}: radius y scale deben ser mayores que 0",
+            entry.name
+        ));
+    }
+    if !(0.0..1.0).contains(&entry.eccentricity) {
+        return Err(format!(
+            "{}: eccentricity debe estar entre 0 y 1 (sin incluir 1)",
+            entry.name
+        ));
+    }
+
+    let shader = SHADER_NAMES
+        .iter()
+        .find(|(name, _)| *name == entry.shader)
+        .map(|(_, shader)| shader.clone())
+        .ok_or_else(|| {
+            let names: Vec<&str> = SHADER_NAMES.iter().map(|(name, _)| *name).collect();
+            format!(
+                "{}: shader desconocido '{}' (opciones: {})",
+                entry.name,
+                entry.shader,
+                names.join(", ")
+            )
+        })?;
+
+    let atmosphere = match &entry.atmosphere {
+        Some(hex) => {
+            let tint =
+                Color::from_hex_str(hex).map_err(|err| format!("{}: {}", entry.name, err))?;
+            let opacity = entry
+                .atmosphere_opacity
+                .unwrap_or(DEFAULT_ATMOSPHERE_OPACITY);
+            Some(Color::new(tint.r, tint.g, tint.b, opacity))
+        }
+        None => None,
+    };
+
+    Ok(Planet {
+        name: entry.name,
+        radius: entry.radius,
+        eccentricity: entry.eccentricity,
+        inclination: entry.inclination,
+        speed: entry.speed,
+        scale: entry.scale,
+        rotation_speed: entry.rotation_speed.unwrap_or(DEFAULT_ROTATION_SPEED),
+        tilt: entry.tilt,
+        shader,
+        has_moon: entry.moon,
+        tidally_locked: entry.tidally_locked,
+        emissive: entry.emissive,
+        atmosphere,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Escribe `text` en un archivo temporal propio de cada prueba, carga el sistema y lo borra
+    fn load(name: &str, text: &str) -> Result<Vec<Planet>, String> {
+        let file = format!("system_{}_{}.toml", name, std::process::id());
+        let path = std::env::temp_dir().join(file);
+        fs::write(&path, text).unwrap();
+        let result = load_system(path.to_str().unwrap());
+        fs::remove_file(&path).unwrap();
+        result
+    }
+
+    fn planet(name: &str, extra: &str) -> String {
+        format!(
+            "[[planet]]\nname = \"{}\"\nradius = 20.0\nspeed = 0.01\nscale = 1.0\n\
+             shader = \"rocky\"\n{}\n",
+            name, extra
+        )
+    }
+
+    #[test]
+    fn valid_file_loads_its_planets_in_order() {
+        let text = planet("Uno", "moon = true\neccentricity = 0.3\ntilt = 20.0")
+            + &planet("Dos", "atmosphere = \"#80C0FF\"");
+        let planets = load("valid", &text).unwrap();
+        assert_eq!(planets.len(), 2);
+        assert_eq!(planets[0].name, "Uno");
+        assert!(planets[0].has_moon && !planets[1].has_moon);
+        assert_eq!(planets[0].eccentricity, 0.3);
+        assert_eq!(planets[1].rotation_speed, DEFAULT_ROTATION_SPEED);
+        assert_eq!(
+            planets[1].atmosphere,
+            Some(Color::new(0x80, 0xC0, 0xFF, DEFAULT_ATMOSPHERE_OPACITY))
+        );
+    }
+
+    #[test]
+    fn missing_file_falls_back_to_the_default_system() {
+        let file = format!("system_missing_{}.toml", std::process::id());
+        let path = std::env::temp_dir().join(file);
+        let planets = load_system(path.to_str().unwrap()).unwrap();
+        let names: Vec<String> = planets.into_iter().map(|planet| planet.name).collect();
+        let defaults: Vec<String> = scene::default_planets()
+            .into_iter()
+            .map(|planet| planet.name)
+            .collect();
+        assert_eq!(names, defaults);
+    }
+
+    #[test]
+    fn empty_file_is_rejected() {
+        assert!(load("empty", "").is_err());
+        assert!(load("no_planets", "planet = []").is_err());
+    }
+
+    #[test]
+    fn exactly_one_moon_is_required() {
+        let none = planet("Uno", "") + &planet("Dos", "");
+        assert!(load("no_moon", &none).is_err());
+        let two = planet("Uno", "moon = true") + &planet("Dos", "moon = true");
+        assert!(load("two_moons", &two).is_err());
+    }
+
+    #[test]
+    fn eccentricity_must_be_in_zero_to_one() {
+        for eccentricity in ["-0.1", "1.0", "1.5"] {
+            let text = planet(
+                "Uno",
+                &format!("moon = true\neccentricity = {}", eccentricity),
+            );
+            assert!(load("eccentricity", &text).is_err(), "{}", eccentricity);
+        }
+    }
+
+    #[test]
+    fn unknown_shader_is_rejected_with_the_options() {
+        let text = planet("Uno", "moon = true").replace("\"rocky\"", "\"lava\"");
+        let err = load("shader", &text).unwrap_err();
+        assert!(err.contains("lava") && err.contains("gas_giant"), "{}", err);
+    }
+}
@@ -1,4 +1,4 @@
-use image::{DynamicImage, GenericImageView, Rgba};
+use image::{DynamicImage, GenericImageView, Rgba, RgbaImage};
 use crate::color::Color;
 
 pub struct Texture {
@@ -7,8 +7,29 @@ pub struct Texture {
 
 impl Texture {
     pub fn new(file_path: &str) -> Self {
-        let image = image::open(file_path).expect("Failed to load texture");
-        Texture { image }
+        Texture::load(file_path).expect("Failed to load texture")
+    }
+
+    // Tablero de ajedrez de `squares` x `squares` casillas, para comprobar que las UV
+    // interpoladas siguen la geometría
+    pub fn checkerboard(size: u32, squares: u32) -> Self {
+        let cell = (size / squares).max(1);
+        let image = RgbaImage::from_fn(size, size, |x, y| {
+            if (x / cell + y / cell).is_multiple_of(2) {
+                Rgba([235, 235, 235, 255])
+            } else {
+                Rgba([40, 40, 40, 255])
+            }
+        });
+        Texture {
+            image: DynamicImage::ImageRgba8(image),
+        }
+    }
+
+    pub fn load(file_path: &str) -> Result<Self, String> {
+        let image = image::open(file_path)
+            .map_err(|err| format!("No se pudo cargar la textura {}: {}", file_path, err))?;
+        Ok(Texture { image })
     }
 
     // Devuelve el color de la textura en coordenadas UV
@@ -20,4 +41,29 @@ impl Texture {
 
         Color::new(pixel[0], pixel[1], pixel[2], pixel[3])  
     }
+
+    // Filtrado bilineal: mezcla los cuatro texels que rodean el punto según la parte
+    // fraccionaria de la coordenada. En horizontal se envuelve (el vecino derecho de la
+    // última columna es la columna 0, así no aparece costura en u = 1); en vertical se
+    // recorta al borde.
+    pub fn get_color_bilinear(&self, u: f32, v: f32) -> Color {
+        let (width, height) = self.image.dimensions();
+        let x = u.rem_euclid(1.0) * width as f32 - 0.5;
+        let y = (v.clamp(0.0, 1.0) * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+
+        let tx = x - x.floor();
+        let ty = y - y.floor();
+        let x0 = (x.floor() as i64).rem_euclid(width as i64) as u32;
+        let x1 = (x0 + 1) % width;
+        let y0 = y.floor() as u32;
+        let y1 = (y0 + 1).min(height - 1);
+
+        let texel = |x: u32, y: u32| {
+            let pixel = self.image.get_pixel(x, y);
+            Color::new(pixel[0], pixel[1], pixel[2], pixel[3])
+        };
+        let top = texel(x0, y0).lerp(&texel(x1, y0), tx);
+        let bottom = texel(x0, y1).lerp(&texel(x1, y1), tx);
+        top.lerp(&bottom, ty)
+    }
 }
\ No newline at end of file
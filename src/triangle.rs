@@ -1,9 +1,33 @@
 use crate::color::Color;
 use crate::fragment::Fragment;
+use crate::framebuffer::ShadingMode;
 use crate::vertex::Vertex;
 use nalgebra_glm::{dot, Vec3};
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+// Posiciones de muestreo dentro del píxel para la cobertura de los bordes (rejilla rotada)
+const EDGE_SAMPLES: [(f32, f32); 4] = [
+    (0.375, 0.125),
+    (0.875, 0.375),
+    (0.125, 0.625),
+    (0.625, 0.875),
+];
+// Distancia máxima (en píxeles) del centro del píxel a una muestra
+const EDGE_SAMPLE_REACH: f32 = 0.75;
+
+// light_dir es la dirección (en el mundo, normalizada) hacia la luz con la que se calcula la
+// intensidad difusa de cada fragmento. En ShadingMode::Flat todos los fragmentos llevan la
+// normal de la cara, el promedio de las de sus tres vértices.
+// Con edge_aa, los píxeles cuyo centro queda fuera pero alguna de las EDGE_SAMPLES dentro
+// dan un fragmento con la cobertura parcial, sombreado en el centro de las muestras que caen
+// dentro. Los interiores no se submuestrean
+pub fn triangle(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    light_dir: &Vec3,
+    shading: ShadingMode,
+    edge_aa: bool,
+) -> Vec<Fragment> {
     let mut fragments = Vec::new();
     let (a, b, c) = (
         v1.transformed_position,
@@ -13,47 +37,104 @@ pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
 
     let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
 
-    let light_dir = Vec3::new(0.0, 0.0, 1.0);
-
     let triangle_area = edge_function(&a, &b, &c);
+    let face_normal = (shading == ShadingMode::Flat).then(|| {
+        (v1.transformed_normal + v2.transformed_normal + v3.transformed_normal).normalize()
+    });
+    // Con estos factores, cada peso baricéntrico pasa a distancia en píxeles a su arista
+    // opuesta (positiva por dentro)
+    let reach = [(c - b).xy(), (a - c).xy(), (b - a).xy()]
+        .map(|edge| triangle_area.abs() / edge.magnitude());
+
+    let inside = |(w1, w2, w3): (f32, f32, f32)| {
+        (0.0..=1.0).contains(&w1) && (0.0..=1.0).contains(&w2) && (0.0..=1.0).contains(&w3)
+    };
+    let fragment_at = |x: i32, y: i32, (w1, w2, w3): (f32, f32, f32)| {
+        // Los atributos se interpolan con corrección de perspectiva (pesos divididos
+        // por la w de recorte de cada vértice); la profundidad NDC ya es lineal en
+        // pantalla y usa los pesos directos
+        let (p1, p2, p3) = (w1 / v1.clip_w, w2 / v2.clip_w, w3 / v3.clip_w);
+        let inv_w = p1 + p2 + p3;
+        let (p1, p2, p3) = (p1 / inv_w, p2 / inv_w, p3 / inv_w);
+
+        let normal = face_normal.unwrap_or_else(|| {
+            let normal = v1.transformed_normal * p1
+                + v2.transformed_normal * p2
+                + v3.transformed_normal * p3;
+            normal.normalize()
+        });
+
+        let intensity = dot(&normal, light_dir).max(0.0);
+
+        let base_color = Color::new(100, 100, 100, 0);
+        let lit_color = base_color * intensity;
+
+        let depth = a.z * w1 + b.z * w2 + c.z * w3;
+
+        let vertex_position = v1.position * p1 + v2.position * p2 + v3.position * p3;
+        let tex_coords = v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3;
+
+        Fragment::new(
+            x as f32,
+            y as f32,
+            lit_color,
+            depth,
+            normal,
+            intensity,
+            vertex_position,
+            (tex_coords.x, tex_coords.y),
+        )
+    };
 
     for y in min_y..=max_y {
         for x in min_x..=max_x {
             let point = Vec3::new(x as f32 + 0.5, y as f32 + 0.5, 0.0);
 
-            let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
-
-            if w1 >= 0.0 && w1 <= 1.0 && w2 >= 0.0 && w2 <= 1.0 && w3 >= 0.0 && w3 <= 1.0 {
-                let normal = v1.transformed_normal * w1
-                    + v2.transformed_normal * w2
-                    + v3.transformed_normal * w3;
-                let normal = normal.normalize();
-
-                let intensity = dot(&normal, &light_dir).max(0.0);
-
-                let base_color = Color::new(100, 100, 100, 0);
-                let lit_color = base_color * intensity;
-
-                let depth = a.z * w1 + b.z * w2 + c.z * w3;
+            let weights = barycentric_coordinates(&point, &a, &b, &c, triangle_area);
 
-                let vertex_position = v1.position * w1 + v2.position * w2 + v3.position * w3;
+            if inside(weights) {
+                fragments.push(fragment_at(x, y, weights));
+                continue;
+            }
 
-                fragments.push(Fragment::new(
-                    x as f32,
-                    y as f32,
-                    lit_color,
-                    depth,
-                    normal,
-                    intensity,
-                    vertex_position,
-                ));
+            // Borde: solo cerca de las aristas alguna muestra puede caer dentro
+            let (w1, w2, w3) = weights;
+            let near_edge = [w1, w2, w3]
+                .iter()
+                .zip(reach)
+                .all(|(w, reach)| w * reach > -EDGE_SAMPLE_REACH);
+            if !edge_aa || !near_edge {
+                continue;
+            }
+            let covered: Vec<Vec3> = EDGE_SAMPLES
+                .iter()
+                .map(|(dx, dy)| Vec3::new(x as f32 + dx, y as f32 + dy, 0.0))
+                .filter(|sample| inside(barycentric_coordinates(sample, &a, &b, &c, triangle_area)))
+                .collect();
+            if covered.is_empty() {
+                continue;
             }
+            let centroid = covered.iter().sum::<Vec3>() / covered.len() as f32;
+            let weights = barycentric_coordinates(&centroid, &a, &b, &c, triangle_area);
+            let mut fragment = fragment_at(x, y, weights);
+            fragment.coverage = covered.len() as f32 / EDGE_SAMPLES.len() as f32;
+            fragments.push(fragment);
         }
     }
 
     fragments
 }
 
+// Orientación del triángulo en pantalla según el signo de su área. La matriz de viewport
+// invierte Y, así que un triángulo antihorario en NDC aparece aquí como horario.
+pub fn is_clockwise(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> bool {
+    edge_function(
+        &v1.transformed_position,
+        &v2.transformed_position,
+        &v3.transformed_position,
+    ) > 0.0
+}
+
 fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
     let min_y = v1.y.min(v2.y).min(v3.y).floor() as i32;
@@ -73,4 +154,4 @@ fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3, area: f32) ->
 
 fn edge_function(a: &Vec3, b: &Vec3, c: &Vec3) -> f32 {
     (c.x - a.x) * (b.y - a.y) - (c.y - a.y) * (b.x - a.x)
-}
\ No newline at end of file
+}
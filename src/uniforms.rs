@@ -0,0 +1,42 @@
+use crate::color::ToneMap;
+use crate::ray_intersect::Sphere;
+use crate::texture::Texture;
+use fastnoise_lite::FastNoiseLite;
+use nalgebra_glm::{Mat4, Vec3, Vec4};
+
+// Datos comunes a los shaders de un cuerpo. Vive en su propio módulo para que el HUD, los
+// shaders y el resto de pasadas lo compartan sin depender de main
+pub struct Uniforms<'a> {
+    pub model_matrix: Mat4,
+    pub view_matrix: Mat4,
+    pub projection_matrix: Mat4,
+    pub viewport_matrix: Mat4,
+    pub time: u32,
+    pub noise: &'a FastNoiseLite,
+    pub camera_eye: Vec3,
+    // Centro del objeto en el mundo: la luz del sol le llega desde (sol - centro)
+    pub center: Vec3,
+    // Cuerpo que puede tapar el sol a este (el planeta a su luna y la luna a su planeta)
+    pub occluder: Option<Sphere>,
+    // Los cuerpos emisivos brillan por sí mismos y no se oscurecen con la iluminación
+    pub emissive: bool,
+    // Texturas de superficie a las que apunta ShaderType::Textured
+    pub textures: &'a [Texture],
+    // Iluminación mínima: la cara nocturna recibe ambient y la diurna sube hasta 1
+    pub ambient: f32,
+    // Operador con el que Color::from_linear comprime el color lineal a 8 bits
+    pub tone_map: ToneMap,
+}
+
+// Proyecta un punto del mundo a pantalla: (x, y, z NDC), o None si queda detrás de la cámara
+pub fn project_to_screen(point: &Vec3, uniforms: &Uniforms) -> Option<Vec3> {
+    let clip = uniforms.projection_matrix
+        * uniforms.view_matrix
+        * Vec4::new(point.x, point.y, point.z, 1.0);
+    if clip.w <= 0.0 {
+        return None;
+    }
+    let ndc = Vec4::new(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = uniforms.viewport_matrix * ndc;
+    Some(Vec3::new(screen.x, screen.y, ndc.z))
+}
@@ -9,6 +9,8 @@ pub struct Vertex {
     pub color: Color,
     pub transformed_position: Vec3,
     pub transformed_normal: Vec3,
+    // w en espacio de recorte, para interpolar con corrección de perspectiva
+    pub clip_w: f32,
 }
 
 impl Vertex {
@@ -20,6 +22,7 @@ impl Vertex {
             color: Color::black(),
             transformed_position: position,
             transformed_normal: normal,
+            clip_w: 1.0,
         }
     }
 }
@@ -33,6 +36,7 @@ impl Default for Vertex {
             color: Color::black(),
             transformed_position: Vec3::new(0.0, 0.0, 0.0),
             transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+            clip_w: 1.0,
         }
     }
 }